@@ -0,0 +1,203 @@
+//! Computer players, and a driver playing games between them without any
+//! interface
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::deck::DeckList;
+use crate::game::{Action, Color, Creature, Outcome, Phase, PickCards, PlayerView};
+use crate::pay;
+use crate::rules::RuleSet;
+
+/// Games that go on for longer than this many actions are a draw, in case
+/// the bots never manage to finish
+pub const MAX_ACTIONS: u32 = 10_000;
+
+/// The way a bot plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Any legal action
+    Random,
+    /// Plays its biggest creatures, attacks with everything, and blocks when
+    /// its creature survives or the damage would be lethal
+    Greedy,
+}
+
+pub trait Bot {
+    /// Pick one of the legal actions, which always include `Pass`
+    fn act(&mut self, view: &PlayerView, legal: &[Action], rng: &mut StdRng) -> Action;
+}
+
+pub fn new_bot(strategy: Strategy) -> Box<dyn Bot> {
+    match strategy {
+        Strategy::Random => Box::new(RandomBot),
+        Strategy::Greedy => Box::new(GreedyBot),
+    }
+}
+
+struct RandomBot;
+
+impl Bot for RandomBot {
+    fn act(&mut self, _view: &PlayerView, legal: &[Action], rng: &mut StdRng) -> Action {
+        let legal: Vec<&Action> = legal.iter().filter(|a| **a != Action::Undo).collect();
+        legal[rng.gen_range(0..legal.len())].clone()
+    }
+}
+
+struct GreedyBot;
+
+impl GreedyBot {
+    fn main_phase(view: &PlayerView, legal: &[Action]) -> Option<Action> {
+        // A gem of the color the creatures in hand need the most
+        let needed = |color: Color| {
+            view.hand.iter()
+                .filter(|c| !c.face.is_gem() && c.color() == color)
+                .count()
+        };
+        let gem = legal.iter()
+            .filter_map(|a| match *a {
+                Action::PlayGem { card } => Some(card),
+                _ => None,
+            })
+            .max_by_key(|&card| needed(view.hand[card].color()));
+        if let Some(card) = gem {
+            return Some(Action::PlayGem { card });
+        }
+
+        // The most expensive creature
+        let creature = legal.iter()
+            .filter_map(|a| match *a {
+                Action::PlayCreature { card, .. } => Some(card),
+                _ => None,
+            })
+            .max_by_key(|&card| view.rules.cost(view.hand[card].face));
+        let card = creature?;
+        let c = &view.hand[card];
+        let gems = pay::suggest(&view.gems, view.rules.cost(c.face)?, c.color(), &view.rules.payment)?;
+        Some(Action::PlayCreature { card, gems })
+    }
+
+    /// Block, if combat deals damage with these rules
+    fn defend(view: &PlayerView, legal: &[Action]) -> Option<Action> {
+        let strength = |creature: &Creature| creature.strength(&view.rules);
+        let unblocked: i32 = view.attackers.iter()
+            .filter(|&&a| !view.blockers.iter().any(|&(_, b)| b == a))
+            .map(|&a| strength(&view.enemy_creatures[a]))
+            .sum::<Option<i32>>()?;
+        let lethal = unblocked >= view.life;
+        legal.iter()
+            .filter(|a| match **a {
+                Action::Block { creature, attacker } => {
+                    lethal || strength(&view.creatures[creature]) > strength(&view.enemy_creatures[attacker])
+                }
+                _ => false,
+            })
+            .max_by_key(|a| match **a {
+                // Block the strongest attacker
+                Action::Block { attacker, .. } => strength(&view.enemy_creatures[attacker]),
+                _ => None,
+            })
+            .cloned()
+    }
+}
+
+impl Bot for GreedyBot {
+    fn act(&mut self, view: &PlayerView, legal: &[Action], _rng: &mut StdRng) -> Action {
+        let action = match view.phase {
+            Phase::Main => GreedyBot::main_phase(view, legal),
+            Phase::Attack => legal.iter().find(|a| matches!(a, Action::Attack { .. })).cloned(),
+            Phase::Defend => GreedyBot::defend(view, legal),
+            _ => None,
+        };
+        action.unwrap_or(Action::Pass)
+    }
+}
+
+/// The end of a game, between bots or with a human
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Played {
+    pub outcome: Outcome,
+    pub turns: u32,
+}
+
+/// Play a game between two bots, the first one going first
+///
+/// The bots keep their starting hands. Everything random comes from the
+/// seed, so the same seed plays the same game.
+pub fn play_game(
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    bots: [&mut dyn Bot; 2],
+    seed: u64,
+) -> Played {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut picks = PickCards::new(decks, rules.clone(), &mut rng);
+    picks.accept(0).unwrap();
+    picks.accept(1).unwrap();
+    let mut game = picks.start().unwrap();
+    game.start();
+    let mut turns = 1;
+    for _ in 0..MAX_ACTIONS {
+        if let Some(outcome) = game.outcome() {
+            return Played { outcome, turns };
+        }
+        let player = game.priority();
+        let legal = game.legal_actions(player);
+        let action = bots[player as usize].act(&game.view_for(player), &legal, &mut rng);
+        let before = game.current_player;
+        game.apply(player, action).unwrap();
+        if game.current_player != before {
+            turns += 1;
+        }
+    }
+    Played { outcome: game.outcome().unwrap_or(Outcome::Draw), turns }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::deck::DeckList;
+    use crate::game::Outcome;
+    use crate::rules::{RuleSet, Strengths};
+
+    use super::{Bot, Strategy, new_bot, play_game};
+
+    #[test]
+    fn test_play_game() {
+        let decks = [DeckList::standard(), DeckList::standard()];
+        // Combat deals damage as a house rule, so the game is decided by play
+        let rules = RuleSet {
+            strengths: Some(Strengths { jack: 2, queen: 4, king: 5, ace: 3 }),
+            ..RuleSet::default()
+        };
+        let mut greedy_wins = 0;
+        let mut turns = Vec::new();
+        for seed in 0..20 {
+            // Each bot goes first in half the games
+            let greedy_seat = (seed % 2) as u32;
+            let play = || {
+                let mut greedy = new_bot(Strategy::Greedy);
+                let mut random = new_bot(Strategy::Random);
+                let bots: [&mut dyn Bot; 2] = match greedy_seat {
+                    0 => [&mut *greedy, &mut *random],
+                    _ => [&mut *random, &mut *greedy],
+                };
+                play_game(&decks, &rules, bots, seed)
+            };
+            let played = play();
+            if played.outcome == Outcome::Win(greedy_seat) {
+                greedy_wins += 1;
+            }
+            turns.push(played.turns);
+
+            // Same seed, same game
+            assert_eq!(play(), played);
+        }
+        assert!(greedy_wins > 15, "greedy bot only won {} games", greedy_wins);
+        // Games end by combat, not all on the same turn
+        turns.sort_unstable();
+        turns.dedup();
+        assert!(turns.len() > 1, "{:?}", turns);
+    }
+}
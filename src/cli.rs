@@ -0,0 +1,1054 @@
+use rand::rngs::StdRng;
+use rand::{SeedableRng, thread_rng};
+use std::io::{BufRead, Write};
+
+use crate::Error;
+use crate::command::{self, Command};
+use superjack::bot::{Bot, Played};
+use superjack::deck::DeckList;
+use superjack::game::{
+    Action, Card, Color, Creature, CreatureStatus, Event, Face, Gem,
+    Outcome, Payment, Phase, PickCards, PlayerView, PublicView, SPECTATOR,
+    check_payment,
+};
+use superjack::hints::{self, Subject};
+use superjack::pay;
+use superjack::puzzle::{self, Progress, Puzzle};
+use superjack::replay::Replay;
+use superjack::rules::RuleSet;
+use superjack::tutorial::{self, Tutorial};
+
+const COMMANDS: &str = "Commands: graveyards, libraries, creature <n>, enemy <n>";
+
+/// Where the player types their answers, and sees the game
+pub struct Console {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    /// What the player is allowed to see, for the commands that work at any
+    /// prompt
+    view: Option<PlayerView>,
+}
+
+impl Console {
+    pub fn new(input: impl BufRead + 'static, output: impl Write + 'static) -> Console {
+        Console { input: Box::new(input), output: Box::new(output), view: None }
+    }
+
+    /// The terminal
+    pub fn stdio() -> Console {
+        Console::new(std::io::stdin().lock(), std::io::stdout())
+    }
+}
+
+pub fn read_line(console: &mut Console, prompt: &str) -> Result<String, Error> {
+    write!(console.output, "{} ", prompt)?;
+    console.output.flush()?;
+    let mut line = String::new();
+    if console.input.read_line(&mut line)? == 0 {
+        return Err(Error::Exit);
+    }
+    Ok(line.trim().to_owned())
+}
+
+/// Read a line, running any inspection command typed instead of an answer
+fn read_input(console: &mut Console, prompt: &str) -> Result<String, Error> {
+    loop {
+        let line = read_line(console, prompt)?;
+        let Some(view) = console.view.take() else {
+            return Ok(line);
+        };
+        let handled = run_command(console, &view, &line);
+        console.view = Some(view);
+        if !handled? {
+            return Ok(line);
+        }
+    }
+}
+
+/// Run an inspection command, returns false if this isn't one
+fn run_command(console: &mut Console, view: &PlayerView, line: &str) -> Result<bool, Error> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words[..] {
+        ["graveyards"] => {
+            writeln!(console.output, "Your graveyard:")?;
+            show_hand(console, &view.graveyard, view.player)?;
+            writeln!(console.output, "Enemy's graveyard:")?;
+            show_hand(console, &view.enemy_graveyard, 1 - view.player)?;
+        }
+        ["libraries"] => {
+            writeln!(console.output, "Your library: {} cards", view.library)?;
+            writeln!(console.output, "Enemy's library: {} cards", view.enemy_library)?;
+        }
+        ["creature", number] | ["enemy", number] => {
+            let (creatures, player) = if words[0] == "creature" {
+                (&view.creatures, view.player)
+            } else {
+                (&view.enemy_creatures, 1 - view.player)
+            };
+            match number.parse::<usize>().ok().and_then(|n| creatures.get(n.wrapping_sub(1))) {
+                Some(creature) => show_creature_details(console, creature, player, view)?,
+                None => writeln!(console.output, "No such creature")?,
+            }
+        }
+        ["help"] => writeln!(console.output, "{}", COMMANDS)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+pub fn read_yes_no(console: &mut Console, prompt: &str, default: Option<bool>) -> Result<bool, Error> {
+    let options = match default {
+        None => "y/n",
+        Some(true) => "Y/n",
+        Some(false) => "y/N",
+    };
+    loop {
+        let response = read_input(console, &format!("{} [{}]", prompt, options))?.to_lowercase();
+        if response == "yes" || response == "y" {
+            return Ok(true);
+        } else if response == "no" || response == "n" {
+            return Ok(false);
+        } else if let (true, Some(default)) = (response.is_empty(), default) {
+            return Ok(default);
+        } else {
+            writeln!(console.output, "I didn't understand {:?}", response)?;
+        }
+    }
+}
+
+pub fn read_number(console: &mut Console, prompt: &str, max: i32, cancellable: bool, empty: bool) -> Result<i32, Error> {
+    let mut options = format!("1-{}", max);
+    if cancellable {
+        options.push_str(" or 0");
+    }
+    if empty {
+        options.push_str(" or enter");
+    }
+    loop {
+        let response = read_input(console, &format!("{} [{}]", prompt, options))?;
+        if response.is_empty() && empty {
+            return Ok(-1);
+        }
+        match response.parse::<i32>() {
+            Ok(0) if cancellable => return Ok(0),
+            Ok(i) if 1 <= i && i <= max => {
+                return Ok(i);
+            }
+            _ => {
+                writeln!(console.output, "Invalid value")?;
+            }
+        }
+    }
+}
+
+pub fn show_hand(console: &mut Console, hand: &[Card], player: u32) -> Result<(), Error> {
+    if hand.is_empty() {
+        writeln!(console.output, "    (no cards)")?;
+    }
+    for (i, card) in hand.iter().enumerate() {
+        writeln!(
+            console.output,
+            "{:>6} - {}{}",
+            i + 1,
+            card,
+            // Show a '*' if card belongs to the other player (mind controlled)
+            if card.player != player { "*" } else { "" },
+        )?;
+    }
+    Ok(())
+}
+
+pub fn show_gems(console: &mut Console, gems: &[Gem]) -> Result<(), Error> {
+    if gems.is_empty() {
+        writeln!(console.output, "    (no gems)")?;
+    }
+    let mut prev_color = None;
+    for (i, gem) in gems.iter().enumerate() {
+        let color = gem.card.color();
+        if prev_color != Some(color) {
+            prev_color = Some(color);
+            writeln!(console.output, "  {}:", color)?;
+        } else {
+            prev_color = Some(color);
+        }
+        writeln!(
+            console.output,
+            "{:>6} - {}{}",
+            i + 1,
+            gem.card,
+            if gem.tapped { " TAPPED" } else { "" },
+        )?;
+    }
+    Ok(())
+}
+
+pub fn show_creatures(console: &mut Console, creatures: &[Creature], player: u32) -> Result<(), Error> {
+    if creatures.is_empty() {
+        writeln!(console.output, "    (no creatures)")?;
+    }
+    for (i, creature) in creatures.iter().enumerate() {
+        write!(console.output, "{:>6} - ", i + 1)?;
+        // Show card(s)
+        for (i, card) in creature.cards.iter().enumerate() {
+            if i > 0 {
+                write!(console.output, ", ")?;
+            }
+            write!(
+                console.output,
+                "{}{}",
+                card,
+                // Show a '*' if card belongs to the other player (mind
+                // controlled)
+                if card.player != player { "*" } else { "" },
+            )?;
+        }
+        writeln!(console.output)?;
+        // Show status
+        match creature.status {
+            CreatureStatus::Ready => {
+                if creature.royal_charge {
+                    writeln!(console.output, "       Ready, Royal Charge")?;
+                } else {
+                    writeln!(console.output, "       Ready")?;
+                }
+            }
+            CreatureStatus::Tapped => writeln!(console.output, "       Tapped")?,
+            CreatureStatus::Untrained => writeln!(console.output, "       Untrained")?,
+        }
+    }
+    Ok(())
+}
+
+pub fn show_status(console: &mut Console, status: &PlayerView) -> Result<(), Error> {
+    let enemy = 1 - status.player;
+    writeln!(console.output)?;
+    writeln!(console.output, "Your life: {}", status.life)?;
+    writeln!(console.output, "Enemy life: {}", status.enemy_life)?;
+    writeln!(
+        console.output,
+        "Enemy has {} cards, {} in library, {} in graveyard",
+        status.enemy_hand, status.enemy_library, status.enemy_graveyard.len(),
+    )?;
+    writeln!(
+        console.output,
+        "You have {} cards in library, {} in graveyard",
+        status.library, status.graveyard.len(),
+    )?;
+    writeln!(console.output, "Enemy's gems:")?;
+    show_gems(console, &status.enemy_gems)?;
+    writeln!(console.output, "Enemy's creatures:")?;
+    show_creatures(console, &status.enemy_creatures, enemy)?;
+    writeln!(console.output, "Your creatures:")?;
+    show_creatures(console, &status.creatures, status.player)?;
+    writeln!(console.output, "Your gems:")?;
+    show_gems(console, &status.gems)?;
+    writeln!(console.output, "Your cards:")?;
+    show_hand(console, &status.hand, status.player)?;
+    writeln!(console.output, "Type help for a list of commands")?;
+    Ok(())
+}
+
+/// List what the player can do with each card and creature, or why not
+pub fn show_hints(console: &mut Console, view: &PlayerView) -> Result<(), Error> {
+    for hint in hints::hints(view) {
+        let what = match hint.subject {
+            Subject::PlayGem { card } => format!("Play {} as a gem", view.hand[card]),
+            Subject::Summon { card } => format!("Summon {}", view.hand[card]),
+            Subject::Attack { creature } => format!("Attack with {}", view.creatures[creature].cards[0]),
+            Subject::Block { creature } => format!("Block with {}", view.creatures[creature].cards[0]),
+            Subject::Sacrifice => "Sacrifice a gem".to_owned(),
+            Subject::Straight => "Play a straight".to_owned(),
+            Subject::Stack => "Stack Jacks".to_owned(),
+            Subject::Pass => "Pass".to_owned(),
+        };
+        match hint.reason {
+            None => writeln!(console.output, "    {}: yes", what)?,
+            Some(reason) => writeln!(console.output, "    {}: no, {}", what, reason)?,
+        }
+    }
+    Ok(())
+}
+
+/// Show the board to a spectator
+pub fn show_board(console: &mut Console, view: &PublicView) -> Result<(), Error> {
+    for (player, p) in view.players.iter().enumerate() {
+        writeln!(console.output)?;
+        writeln!(
+            console.output,
+            "Player {}{}: life {}, {} cards in hand, {} in library, {} in graveyard",
+            player + 1,
+            if view.current_player == player as u32 { " (playing)" } else { "" },
+            p.life, p.hand, p.library, p.graveyard.len(),
+        )?;
+        writeln!(console.output, "Gems:")?;
+        show_gems(console, &p.gems)?;
+        writeln!(console.output, "Creatures:")?;
+        show_creatures(console, &p.creatures, player as u32)?;
+    }
+    Ok(())
+}
+
+/// Show everything about a creature: its cards, equipment, and status
+fn show_creature_details(console: &mut Console, creature: &Creature, player: u32, view: &PlayerView) -> Result<(), Error> {
+    let mark = |card: &Card| if card.player != player { "*" } else { "" };
+    writeln!(console.output, "Creature {}{}:", creature.cards[0], mark(&creature.cards[0]))?;
+    if creature.cards.len() > 1 {
+        write!(console.output, "    Stacked with")?;
+        for card in &creature.cards[1..] {
+            write!(console.output, " {}{}", card, mark(card))?;
+        }
+        writeln!(console.output)?;
+    }
+    for [first, second] in &creature.equipment {
+        writeln!(console.output, "    Equipped with {}{} and {}{}", first, mark(first), second, mark(second))?;
+    }
+    if let Some(strength) = creature.strength(&view.rules) {
+        writeln!(console.output, "    Strength {}", strength)?;
+    }
+    let status = match creature.status {
+        CreatureStatus::Ready => "Ready",
+        CreatureStatus::Tapped => "Tapped",
+        CreatureStatus::Untrained => "Untrained",
+    };
+    if creature.royal_charge {
+        writeln!(console.output, "    {}, Royal Charge", status)?;
+    } else {
+        writeln!(console.output, "    {}", status)?;
+    }
+    Ok(())
+}
+
+/// Describe an event, as seen by the given player or `SPECTATOR`
+pub fn describe_event(event: &Event, viewer: u32) -> String {
+    let who = |player: u32, you: &'static str, enemy: &'static str| {
+        if player == viewer {
+            you.to_owned()
+        } else if viewer == SPECTATOR {
+            enemy.replacen("Enemy", &format!("Player {}", player + 1), 1)
+        } else {
+            enemy.to_owned()
+        }
+    };
+    match *event {
+        Event::TurnStarted { player } => format!("\nPlayer {}'s turn\n", player + 1),
+        Event::CardDrawn { player, card: Some(ref card) } => {
+            format!("{} a card: {}", who(player, "You draw", "Enemy draws"), card)
+        }
+        Event::CardDrawn { player, card: None } => {
+            format!("{} a card", who(player, "You draw", "Enemy draws"))
+        }
+        Event::LibraryEmpty { player } => {
+            format!("{} draw, no cards left", who(player, "You can't", "Enemy can't"))
+        }
+        Event::GemPlayed { player, ref card } => {
+            format!("{} gem {}", who(player, "You add", "Enemy adds"), card)
+        }
+        Event::CreaturePlayed { player, ref card, royal_charge } => {
+            let mut text = format!("{} creature {}", who(player, "You add", "Enemy adds"), card);
+            if royal_charge {
+                text.push_str("\nCreature has Royal Charge!");
+            }
+            text
+        }
+        Event::PhaseChanged { phase } => format!("Now in {}", phase),
+        Event::Undone { player } => {
+            format!("{} back the last action", who(player, "You take", "Enemy takes"))
+        }
+        Event::AttackerDeclared { player, creature } => {
+            format!(
+                "{} with creature {}",
+                who(player, "You attack", "Enemy attacks"),
+                creature + 1,
+            )
+        }
+        Event::BlockerDeclared { player, creature, attacker } => {
+            format!(
+                "{} creature {} with creature {}",
+                who(player, "You block", "Enemy blocks"),
+                attacker + 1,
+                creature + 1,
+            )
+        }
+        Event::PlayerDamaged { player, damage, life } => {
+            format!(
+                "{} {} damage, life is now {}",
+                who(player, "You take", "Enemy takes"),
+                damage,
+                life,
+            )
+        }
+        Event::CreatureDestroyed { player, ref cards } => {
+            let mut text = who(player, "You lose creature", "Enemy loses creature");
+            for card in cards {
+                text.push_str(&format!(" {}", card));
+            }
+            text
+        }
+    }
+}
+
+/// Print an event, as seen by the given player
+pub fn show_event(console: &mut Console, event: &Event, viewer: u32) -> Result<(), Error> {
+    let text = describe_event(event, viewer);
+    writeln!(console.output, "{}", text)?;
+    Ok(())
+}
+
+pub fn show_outcome(console: &mut Console, outcome: Outcome) -> Result<(), Error> {
+    writeln!(console.output, "{}", outcome)?;
+    Ok(())
+}
+
+/// Ask the player what to do next
+///
+/// Several actions can be given at once, such as attacking with multiple
+/// creatures, they should be performed in order. Instant phases are passed
+/// without asking, the caller passes the other phases where that is the only
+/// legal action.
+pub fn choose_action(console: &mut Console, status: &PlayerView) -> Result<Vec<Action>, Error> {
+    with_view(console, status, |console| ask_action(console, status))
+}
+
+/// Run prompts with the inspection commands working on this view
+fn with_view<T>(console: &mut Console, status: &PlayerView, prompts: impl FnOnce(&mut Console) -> T) -> T {
+    console.view = Some(status.clone());
+    let result = prompts(console);
+    console.view = None;
+    result
+}
+
+fn ask_action(console: &mut Console, status: &PlayerView) -> Result<Vec<Action>, Error> {
+    match status.phase {
+        // Attack and Defend are passed by the caller when there is nothing to
+        // do, the player might want to undo their last attack or block
+        Phase::Main | Phase::Attack | Phase::Defend => {}
+        // TODO: Instant actions
+        Phase::AttackInstant | Phase::DefendInstant
+        | Phase::SecondMain | Phase::FinalInstant => return Ok(vec![Action::Pass]),
+    }
+
+    show_status(console, status)?;
+    if status.phase == Phase::Defend {
+        writeln!(console.output, "Attacking creatures:")?;
+        for &attacker in &status.attackers {
+            writeln!(
+                console.output,
+                "{:>6} - {}",
+                attacker + 1,
+                status.enemy_creatures[attacker].cards[0],
+            )?;
+        }
+    }
+    loop {
+        let line = read_line(console, &format!("{}>", status.phase))?;
+        let command = match command::parse(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                if !run_command(console, status, &line)? {
+                    writeln!(console.output, "{}", e)?;
+                }
+                continue;
+            }
+        };
+        match command {
+            Command::Play { card, gems } => {
+                if status.phase != Phase::Main {
+                    writeln!(console.output, "You can only play cards during the main phase")?;
+                } else if card >= status.hand.len() {
+                    writeln!(console.output, "No such card")?;
+                } else if !gems.is_empty() {
+                    return Ok(vec![Action::PlayCreature { card, gems }]);
+                } else if let Some(action) = play_card(console, status, card)? {
+                    return Ok(vec![action]);
+                }
+            }
+            Command::Sacrifice { .. } => writeln!(console.output, "Sacrificing gems is not supported by the engine yet")?,
+            Command::Straight { .. } => writeln!(console.output, "Straights are not supported by the engine yet")?,
+            Command::Stack { .. } => writeln!(console.output, "Stacking jacks is not supported by the engine yet")?,
+            Command::Attack { creatures } => {
+                return Ok(creatures.into_iter().map(|creature| Action::Attack { creature }).collect());
+            }
+            Command::Block { creature, attacker } => {
+                return Ok(vec![Action::Block { creature, attacker }]);
+            }
+            Command::Pass => {
+                if read_yes_no(console, &format!("Pass, ending the {}?", status.phase), Some(false))? {
+                    return Ok(vec![Action::Pass]);
+                }
+            }
+            Command::Undo => return Ok(vec![Action::Undo]),
+            Command::Hint => show_hints(console, status)?,
+            Command::Help => writeln!(console.output, "{}", command::HELP)?,
+            Command::Status => show_status(console, status)?,
+        }
+    }
+}
+
+/// Try to play a card, asking relevant questions.
+///
+/// Returns Ok(None) if the card can't be played after all.
+fn play_card(console: &mut Console, status: &PlayerView, card: usize) -> Result<Option<Action>, Error> {
+    let card_ref = &status.hand[card];
+    match status.rules.cost(card_ref.face) {
+        None => Ok(Some(Action::PlayGem { card })),
+        Some(cost) => {
+            if card_ref.face == Face::Ace && read_yes_no(console, "Play as spell?", None)? {
+                writeln!(console.output, "Not implemented yet")?;
+                return Ok(None);
+            }
+            Ok(pick_gems(console, status, cost, card_ref.color())?
+                .map(|gems| Action::PlayCreature { card, gems }))
+        }
+    }
+}
+
+/// Have the player select gems to pay for something
+///
+/// Returns the positions of the selected gems, or None if cancelled.
+fn pick_gems(console: &mut Console, status: &PlayerView, cost: u32, color: Color) -> Result<Option<Vec<usize>>, Error> {
+    // TODO: Gem sacrifice 4 or 5 to "mine" (+3 energy)
+
+    // Create vector of same size as gems
+    // For gems we can't select: None
+    // For gems we can select Some(true) if selected, Some(false) if unselected
+    let mut choices = Vec::new();
+    let mut num_choices = 0;
+    for gem in &status.gems {
+        if !gem.tapped && gem.card.color() == color {
+            choices.push(Some(false));
+            num_choices += 1;
+        } else {
+            choices.push(None);
+        }
+    }
+    if num_choices < cost {
+        writeln!(console.output, "You need {} untapped {} gems", cost, color)?;
+        return Ok(None);
+    }
+
+    // Start from the suggested payment, which the player can just confirm
+    let suggested = pay::suggest(&status.gems, cost, color, &status.rules.payment)
+        .unwrap_or_default();
+    for &gem in &suggested {
+        choices[gem] = Some(true);
+    }
+    writeln!(console.output, "Press enter to pay with the suggested gems, or pick them yourself")?;
+
+    // Loop until gems are selected and confirmed
+    loop {
+        for (i, (gem, selected)) in
+            status.gems.iter().zip(&choices)
+                .filter_map(|(gem, &choice)| choice.map(|c| (gem, c)))
+                .enumerate()
+        {
+            writeln!(
+                console.output,
+                "{:>6} - {} {}",
+                i + 1,
+                if selected { "[x]" } else { "[ ]" },
+                gem.card,
+            )?;
+        }
+        let selected: Vec<usize> = choices.iter().enumerate()
+            .filter(|&(_, &selected)| selected == Some(true))
+            .map(|(i, _)| i)
+            .collect();
+        let payment = check_payment(&status.gems, &selected, cost, color).ok();
+        let royal_charge = match payment {
+            Some(Payment { royal_charge: true, .. }) => ", Royal Charge",
+            _ => "",
+        };
+        match read_number(
+            console,
+            &format!("Pick gems ({}/{}{})", selected.len(), cost, royal_charge),
+            num_choices as i32,
+            true,
+            payment.is_some(),
+        )? {
+            // Cancelled
+            0 => return Ok(None),
+            // Confirmed
+            -1 => return Ok(Some(selected)),
+            // Toggle a gem
+            i => {
+                match choices.iter_mut()
+                    .filter_map(|s| s.as_mut())
+                    .nth((i - 1) as usize)
+                {
+                    Some(selected) => *selected = !*selected,
+                    None => writeln!(console.output, "No such gem")?,
+                }
+            }
+        }
+    }
+}
+
+pub fn wait_for_enter(console: &mut Console, prompt: &str) -> Result<(), Error> {
+    read_line(console, prompt).map(|_| ())
+}
+
+fn clear_screen(console: &mut Console) -> Result<(), Error> {
+    write!(console.output, "\x1b[2J\x1b[H")?;
+    console.output.flush()?;
+    Ok(())
+}
+
+/// Keeps a player's private information off the screen while the other
+/// player is looking, when two players share the terminal
+struct Hotseat {
+    enabled: bool,
+    /// The player whose cards are on the screen
+    shown: Option<u32>,
+}
+
+impl Hotseat {
+    /// Clear the screen and wait for the given player, before showing their
+    /// cards
+    fn show(&mut self, console: &mut Console, player: u32) -> Result<(), Error> {
+        if !self.enabled || self.shown == Some(player) {
+            return Ok(());
+        }
+        if self.shown.is_some() {
+            wait_for_enter(console, "Press enter to hide your cards")?;
+        }
+        clear_screen(console)?;
+        wait_for_enter(console, &format!("Player {} press enter", player + 1))?;
+        clear_screen(console)?;
+        self.shown = Some(player);
+        Ok(())
+    }
+}
+
+/// Have the player look at their starting hand and maybe Mulligan
+fn pick_starting_hand(console: &mut Console, player: u32, picks: &mut PickCards, screen: &mut Hotseat) -> Result<(), Error> {
+    let mut rng = thread_rng();
+    screen.show(console, player)?;
+    loop {
+        writeln!(console.output, "\nPlayer {} draws cards:", player + 1)?;
+        show_hand(console, picks.hand(player), player)?;
+
+        // Mulligan?
+        if !picks.can_mulligan(player) {
+            break;
+        }
+        let view = picks.view_for(player);
+        if !with_view(console, &view, |console| read_yes_no(console, "Mulligan?", Some(false)))? {
+            break;
+        }
+        picks.mulligan(player, &mut rng).unwrap();
+    }
+    if !picks.is_ready(player) {
+        picks.accept(player).unwrap();
+    }
+    Ok(())
+}
+
+/// Play a game with two players sharing this terminal
+///
+/// In hotseat mode, the screen is cleared before showing a player's cards.
+pub fn play(
+    console: &mut Console,
+    hotseat: bool,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    record: Option<&str>,
+) -> Result<Outcome, Error> {
+    play_with(console, hotseat, decks, rules, record, [None, None]).map(|played| played.outcome)
+}
+
+/// Play a game at this terminal, the computer playing the seats given a bot
+///
+/// Against a bot, the player only sees what they are allowed to. Bots keep
+/// their starting hands.
+pub fn play_with(
+    console: &mut Console,
+    hotseat: bool,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    record: Option<&str>,
+    mut bots: [Option<Box<dyn Bot>>; 2],
+) -> Result<Played, Error> {
+    writeln!(console.output, "New game!")?;
+    let mut screen = Hotseat { enabled: hotseat, shown: None };
+    let mut rng = StdRng::from_entropy();
+    // The only player at the terminal, when playing against a bot
+    let alone = match bots {
+        [None, Some(_)] => Some(0),
+        [Some(_), None] => Some(1),
+        _ => None,
+    };
+
+    // Pick players' starting hands
+    let mut picks = PickCards::new(decks, rules.clone(), &mut rng);
+    for player in 0..2 {
+        if bots[player as usize].is_some() {
+            picks.accept(player).unwrap();
+        } else {
+            pick_starting_hand(console, player, &mut picks, &mut screen)?;
+        }
+    }
+
+    let mut game = picks.start().unwrap();
+    let mut replay = Replay::new(&game);
+    let mut events = game.start();
+    // In hotseat mode, events are shown to each player when it's their turn
+    // to act
+    let mut pending: [Vec<Event>; 2] = Default::default();
+    let mut turns = 1;
+    let mut current = game.current_player;
+
+    // Loop until someone wins
+    loop {
+        if let Some(path) = record {
+            std::fs::write(path, replay.to_json())?;
+        }
+        if game.current_player != current {
+            current = game.current_player;
+            turns += 1;
+        }
+        if hotseat {
+            for queue in &mut pending {
+                queue.extend(events.iter().cloned());
+            }
+        } else if let Some(player) = alone {
+            for event in &events {
+                show_event(console, &event.redact_for(player), player)?;
+            }
+        } else {
+            for event in &events {
+                show_event(console, event, game.current_player)?;
+            }
+        }
+        if let Some(outcome) = game.outcome() {
+            if let Some(player) = screen.shown {
+                for event in &pending[player as usize] {
+                    show_event(console, &event.redact_for(player), player)?;
+                }
+            }
+            show_outcome(console, outcome)?;
+            return Ok(Played { outcome, turns });
+        }
+
+        let player = game.priority();
+        if let Some(ref mut bot) = bots[player as usize] {
+            let action = bot.act(&game.view_for(player), &game.legal_actions(player), &mut rng);
+            events = game.apply(player, action.clone()).unwrap();
+            replay.record(player, action);
+            continue;
+        }
+
+        // Pass without asking if there is nothing else to do
+        if game.phase != Phase::Main && game.legal_actions(player) == [Action::Pass] {
+            events = game.apply(player, Action::Pass).unwrap();
+            replay.record(player, Action::Pass);
+            continue;
+        }
+
+        screen.show(console, player)?;
+        for event in pending[player as usize].drain(..) {
+            show_event(console, &event.redact_for(player), player)?;
+        }
+        events = Vec::new();
+        for action in choose_action(console, &game.view_for(player))? {
+            match game.apply(player, action.clone()) {
+                Ok(e) => {
+                    events.extend(e);
+                    replay.record(player, action);
+                }
+                Err(e) => {
+                    writeln!(console.output, "{}", e)?;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Have the player solve a puzzle, the enemy defending as well as possible
+pub fn solve(console: &mut Console, puzzle: &Puzzle) -> Result<(), Error> {
+    writeln!(console.output, "{}", puzzle.title)?;
+    if !puzzle.description.is_empty() {
+        writeln!(console.output, "{}", puzzle.description)?;
+    }
+    writeln!(console.output, "Win before the end of your turn, the enemy has {} life.", puzzle.enemy_life)?;
+    let mut game = puzzle.game();
+    let mut events: Vec<Event> = Vec::new();
+    loop {
+        for event in &events {
+            show_event(console, &event.redact_for(puzzle::PLAYER), puzzle::PLAYER)?;
+        }
+        match puzzle::progress(&game) {
+            Progress::Solved => {
+                writeln!(console.output, "Puzzle solved!")?;
+                return Ok(());
+            }
+            Progress::Failed => {
+                writeln!(console.output, "Your turn is over, the puzzle is not solved.")?;
+                return Ok(());
+            }
+            Progress::Playing => {}
+        }
+
+        let player = game.priority();
+        if player != puzzle::PLAYER {
+            events = game.apply(player, puzzle::defense(&game)).unwrap();
+            continue;
+        }
+        if game.phase != Phase::Main && game.legal_actions(player) == [Action::Pass] {
+            events = game.apply(player, Action::Pass).unwrap();
+            continue;
+        }
+        events = Vec::new();
+        for action in choose_action(console, &game.view_for(player))? {
+            match game.apply(player, action) {
+                Ok(e) => events.extend(e),
+                Err(e) => {
+                    writeln!(console.output, "{}", e)?;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Go through the lessons of the tutorial
+pub fn tutorial(console: &mut Console) -> Result<(), Error> {
+    let lessons = tutorial::lessons();
+    let count = lessons.len();
+    for (i, lesson) in lessons.into_iter().enumerate() {
+        writeln!(console.output, "\nLesson {}/{}: {}\n", i + 1, count, lesson.title)?;
+        writeln!(console.output, "{}", lesson.intro)?;
+        let mut tutorial = Tutorial::new(lesson);
+        while let Some(step) = tutorial.step() {
+            writeln!(console.output, "\n{}", step.text)?;
+            let actions = choose_action(console, &tutorial.game().view_for(tutorial::PLAYER))?;
+            for action in actions {
+                match tutorial.apply(action) {
+                    Ok(events) => {
+                        for event in &events {
+                            show_event(console, &event.redact_for(tutorial::PLAYER), tutorial::PLAYER)?;
+                        }
+                    }
+                    Err(e) => {
+                        writeln!(console.output, "{}", e)?;
+                        break;
+                    }
+                }
+            }
+        }
+        writeln!(console.output, "\n{}", tutorial.lesson().conclusion)?;
+        if i + 1 < count {
+            wait_for_enter(console, "Press enter for the next lesson")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::cell::RefCell;
+    use std::io::{BufRead, Cursor, Read, Write};
+    use std::rc::Rc;
+    use superjack::bot::{Strategy, new_bot};
+    use superjack::deck::DeckList;
+    use superjack::game::{Action, CreatureStatus, Face, Game, Outcome, Phase, Player, Suit, deal};
+    use superjack::rules::{Decking, RuleSet};
+    use superjack::scenario::Scenario;
+
+    use crate::Error;
+    use super::{Console, choose_action, play_with, read_line, run_command};
+
+    /// Everything written to the console
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Output {
+        /// The lines on the screen, without the codes clearing it
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+                .replace("\x1b[2J\x1b[H", "")
+                .lines()
+                .map(|line| line.trim().to_owned())
+                .collect()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Lines typed by the tests, echoed to the output like a terminal does
+    struct Script {
+        input: Cursor<Vec<u8>>,
+        output: Output,
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.input.read(buf)?;
+            self.output.write_all(&buf[..read])?;
+            Ok(read)
+        }
+    }
+
+    impl BufRead for Script {
+        fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+            self.input.fill_buf()
+        }
+
+        fn consume(&mut self, amount: usize) {
+            let typed = &self.input.fill_buf().unwrap()[..amount];
+            self.output.0.borrow_mut().extend_from_slice(typed);
+            self.input.consume(amount);
+        }
+    }
+
+    /// A console typing the given lines, then reaching the end of input
+    fn console(lines: &[&str]) -> (Console, Output) {
+        let output = Output::default();
+        let typed: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let script = Script { input: Cursor::new(typed.into_bytes()), output: output.clone() };
+        (Console::new(script, output.clone()), output)
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (library1, hand1) = deal(0, &mut rng);
+        let (library2, hand2) = deal(1, &mut rng);
+        let game = Game::new([Player::new(library1, hand1), Player::new(library2, hand2)]);
+        let view = game.view_for(0);
+        let (mut console, _) = console(&[]);
+        assert!(run_command(&mut console, &view, "graveyards").unwrap());
+        assert!(run_command(&mut console, &view, " libraries ").unwrap());
+        assert!(run_command(&mut console, &view, "creature 1").unwrap());
+        assert!(run_command(&mut console, &view, "enemy x").unwrap());
+        assert!(!run_command(&mut console, &view, "y").unwrap());
+        assert!(!run_command(&mut console, &view, "2").unwrap());
+        assert!(!run_command(&mut console, &view, "").unwrap());
+    }
+
+    #[test]
+    fn test_undo_attack() {
+        let mut game = Scenario::new()
+            .rules(RuleSet { undo: true, ..RuleSet::default() })
+            .creature(0, "J♠", CreatureStatus::Ready)
+            .build();
+        game.apply(0, Action::Pass).unwrap();
+        assert_eq!(game.phase, Phase::Attack);
+        game.apply(0, Action::Attack { creature: 0 }).unwrap();
+
+        // No creature is left to attack with, but the attack can be undone
+        let (mut console, _) = console(&["undo"]);
+        assert_eq!(choose_action(&mut console, &game.view_for(0)).unwrap(), [Action::Undo]);
+        game.apply(0, Action::Undo).unwrap();
+        assert_eq!(game.players[0].creatures[0].status, CreatureStatus::Ready);
+        assert!(game.attackers.is_empty());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let game = Scenario::new().gems(0, "2♥").build();
+        let (mut console, _) = console(&["sac 1", "straight 1", "stack 1 2", "pass", "y"]);
+        // The commands are understood, but the player is asked again
+        assert_eq!(choose_action(&mut console, &game.view_for(0)).unwrap(), [Action::Pass]);
+        assert!(matches!(read_line(&mut console, ">"), Err(Error::Exit)));
+    }
+
+    #[test]
+    fn test_play_bot() {
+        // Keep the starting hand, then pass every turn
+        let mut lines = vec!["n"];
+        for _ in 0..1000 {
+            lines.extend(["pass", "y"]);
+        }
+        let (mut console, _) = console(&lines);
+        let decks = [DeckList::standard(), DeckList::standard()];
+        let bots = [None, Some(new_bot(Strategy::Greedy))];
+        // The player going first runs out of cards first
+        let rules = RuleSet { decking: Decking::Lose, ..RuleSet::default() };
+        let played = play_with(&mut console, false, &decks, &rules, None, bots).unwrap();
+        assert_eq!(played.outcome, Outcome::Win(1));
+        assert!(played.turns > 2);
+    }
+
+    #[test]
+    fn test_hotseat() {
+        // Free jacks, so players have creatures to attack and block with.
+        // Each player has their own suit, to tell whose cards are shown.
+        let mut rules = RuleSet { hand_size: 2, decking: Decking::Lose, ..RuleSet::default() };
+        rules.costs.jack = 0;
+        let decks = [
+            DeckList { cards: vec![(Face::Jack, Suit::Hearts); 8] },
+            DeckList { cards: vec![(Face::Jack, Suit::Spades); 8] },
+        ];
+        let (mut console, output) = console(&[
+            // Player 1 takes a mulligan, player 2 keeps their hand
+            "", "y",
+            "", "", "n",
+            // Player 1 plays a creature
+            "", "", "play 1", "", "pass", "y",
+            // Player 2 as well
+            "", "", "play 1", "", "pass", "y",
+            // Player 1 attacks, player 2 is asked to block
+            "", "", "pass", "y", "attack 1",
+            "", "", "block 1 1",
+        ]);
+        assert!(matches!(play_with(&mut console, true, &decks, &rules, None, [None, None]), Err(Error::Exit)));
+
+        // The screen is handed over for mulligans, turns, and blocking in the
+        // middle of the enemy's turn
+        let lines = output.lines();
+        let handovers: Vec<&str> = lines.iter()
+            .map(|line| line.as_str())
+            .filter(|line| line.ends_with("press enter") || line.ends_with("your cards"))
+            .collect();
+        let hide = "Press enter to hide your cards";
+        assert_eq!(handovers, [
+            "Player 1 press enter", hide, "Player 2 press enter",
+            hide, "Player 1 press enter", hide, "Player 2 press enter",
+            hide, "Player 1 press enter", hide, "Player 2 press enter",
+        ]);
+        let blocking = lines.iter().rposition(|line| line == "Player 2 press enter").unwrap();
+        assert!(lines[blocking..].iter().any(|line| line.starts_with("defense>")));
+        assert!(lines[..blocking].iter().any(|line| line.starts_with("attack>")));
+
+        // Cards are only shown after the player they belong to pressed enter
+        let suits = ["♥", "♠"];
+        let mut at_terminal = None;
+        let mut hand = false;
+        for line in &lines {
+            if line == hide {
+                at_terminal = None;
+            } else if let Some(player) = line.strip_prefix("Player ")
+                .and_then(|l| l.strip_suffix(" press enter"))
+            {
+                at_terminal = Some(player.parse::<usize>().unwrap() - 1);
+            } else if let Some(player) = line.strip_prefix("Player ")
+                .and_then(|l| l.strip_suffix(" draws cards:"))
+            {
+                assert_eq!(at_terminal, Some(player.parse::<usize>().unwrap() - 1), "{}", line);
+            } else if let Some(card) = line.strip_prefix("You draw a card: ") {
+                assert!(card.ends_with(suits[at_terminal.unwrap()]), "{}", line);
+            } else if line.starts_with("Enemy draws a card: ") {
+                panic!("{}", line);
+            } else if line == "Your cards:" {
+                hand = true;
+            } else if line.starts_with("Type help") {
+                hand = false;
+            } else if hand {
+                let enemy = suits[1 - at_terminal.unwrap()];
+                assert!(!line.contains(enemy), "{}", line);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Commands typed at the game prompt, such as `play 3` or `attack 1 3`
+//!
+//! Command names can be shortened to any unambiguous prefix. Numbers are the
+//! ones shown on screen, starting at 1.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Play a card from the hand, optionally with the gems to pay for it
+    Play { card: usize, gems: Vec<usize> },
+    Sacrifice { gem: usize },
+    Straight { gems: Vec<usize> },
+    Stack { creatures: Vec<usize> },
+    Attack { creatures: Vec<usize> },
+    Block { creature: usize, attacker: usize },
+    Pass,
+    /// Take back the last action, if the rules allow it
+    Undo,
+    /// Explain what can be done, and why not the rest
+    Hint,
+    Help,
+    Status,
+}
+
+/// Names of the commands, including aliases
+pub const NAMES: &[&str] = &[
+    "play", "sacrifice", "sac", "straight", "stack", "attack", "block", "pass",
+    "undo", "help", "hint", "status",
+];
+
+pub const HELP: &str = "\
+Commands:
+    play <card> [gems...]   Play a card from your hand
+    sac <gem>               Sacrifice a gem (not supported yet)
+    straight <gems...>      Play a straight from your gems (not supported yet)
+    stack <creatures...>    Stack jacks (not supported yet)
+    attack <creatures...>   Attack with creatures
+    block <creature> <attacker>
+                            Block an attacker with a creature
+    pass                    Pass priority, ending the phase
+    undo                    Take back your last action, in casual games
+    hint                    List what you can do, and why you can't do the rest
+    status                  Show the board again
+    graveyards, libraries   Show the graveyards, or the size of the libraries
+    creature <n>, enemy <n> Show one of your creatures, or the enemy's
+    help                    Show this message
+Commands can be shortened, for example `pl 3` or `at 1 2`.";
+
+/// Find the command a possibly shortened name refers to
+fn resolve(name: &str) -> Result<&'static str, String> {
+    let matches: Vec<&str> = NAMES.iter().copied().filter(|n| n.starts_with(name)).collect();
+    match matches[..] {
+        [] => Err(format!("Unknown command {:?}, type help for a list", name)),
+        [single] => Ok(single),
+        // "sac" is an alias of "sacrifice"
+        ["sacrifice", "sac"] => Ok("sacrifice"),
+        // "h" was there first
+        ["help", "hint"] => Ok("help"),
+        _ => Err(format!("{:?} could be {}", name, matches.join(", "))),
+    }
+}
+
+/// Parse 1-based numbers into positions
+fn numbers(args: &[&str]) -> Result<Vec<usize>, String> {
+    args.iter()
+        .map(|arg| match arg.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n - 1),
+            _ => Err(format!("Invalid number {:?}", arg)),
+        })
+        .collect()
+}
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&name, args)) = words.split_first() else {
+        return Err("Type a command, or help for a list".to_owned());
+    };
+    let name = resolve(&name.to_lowercase())?;
+    let args = numbers(args)?;
+    Ok(match (name, &args[..]) {
+        ("play", [card, gems @ ..]) => Command::Play { card: *card, gems: gems.to_vec() },
+        ("sacrifice", &[gem]) => Command::Sacrifice { gem },
+        ("straight", [_, ..]) => Command::Straight { gems: args },
+        ("stack", [_, _, ..]) => Command::Stack { creatures: args },
+        ("attack", [_, ..]) => Command::Attack { creatures: args },
+        ("block", &[creature, attacker]) => Command::Block { creature, attacker },
+        ("pass", []) => Command::Pass,
+        ("undo", []) => Command::Undo,
+        ("help", []) => Command::Help,
+        ("hint", []) => Command::Hint,
+        ("status", []) => Command::Status,
+        _ => return Err(format!("Usage: {}", usage(name))),
+    })
+}
+
+fn usage(name: &'static str) -> &'static str {
+    match name {
+        "play" => "play <card> [gems...]",
+        "sacrifice" => "sac <gem>",
+        "straight" => "straight <gems...>",
+        "stack" => "stack <creatures...>",
+        "attack" => "attack <creatures...>",
+        "block" => "block <creature> <attacker>",
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, parse};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("play 3"), Ok(Command::Play { card: 2, gems: vec![] }));
+        assert_eq!(parse("  PLAY 3 1 2"), Ok(Command::Play { card: 2, gems: vec![0, 1] }));
+        assert_eq!(parse("sac 2"), Ok(Command::Sacrifice { gem: 1 }));
+        assert_eq!(parse("sacri 2"), Ok(Command::Sacrifice { gem: 1 }));
+        assert_eq!(parse("straight 1 2 3"), Ok(Command::Straight { gems: vec![0, 1, 2] }));
+        assert_eq!(parse("stack 1 2"), Ok(Command::Stack { creatures: vec![0, 1] }));
+        assert_eq!(parse("at 1 3"), Ok(Command::Attack { creatures: vec![0, 2] }));
+        assert_eq!(parse("block 2 1"), Ok(Command::Block { creature: 1, attacker: 0 }));
+        assert_eq!(parse("pass"), Ok(Command::Pass));
+        assert_eq!(parse("u"), Ok(Command::Undo));
+        assert_eq!(parse("h"), Ok(Command::Help));
+        assert_eq!(parse("hi"), Ok(Command::Hint));
+        assert_eq!(parse("stat"), Ok(Command::Status));
+
+        assert!(parse("").is_err());
+        assert!(parse("dance").is_err());
+        assert_eq!(parse("st 1"), Err("\"st\" could be straight, stack, status".to_owned()));
+        assert_eq!(parse("p 1"), Err("\"p\" could be play, pass".to_owned()));
+        assert_eq!(parse("play 0"), Err("Invalid number \"0\"".to_owned()));
+        assert_eq!(parse("play"), Err("Usage: play <card> [gems...]".to_owned()));
+        assert!(parse("block 1").is_err());
+        assert!(parse("stack 1").is_err());
+        assert!(parse("pass 1").is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Checking the engine against the state charts in `formal-rules/`
+//!
+//! The PlantUML files are parsed into a graph of transitions, and random games
+//! record the phase transitions the engine takes. A transition that is not in
+//! the charts fails the test, and the transitions of the charts that no game
+//! took are reported, so the code and the formal rules stay in sync.
+//!
+//! `playing.uml` is checked against the phases of `Game`, and `top-level.uml`
+//! against the picks of `PickCards` and the end of the game. Games only end
+//! the way the chart says with combat damage, so the top-level games use the
+//! house-rule strengths.
+//! `instant-actions.uml` and `instant.uml` are not checked: the engine has no
+//! instant actions yet, so the phases where they happen only allow passing,
+//! which is checked instead.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::deck::DeckList;
+use crate::game::{Action, Game, Phase, PickCards, Player, deal};
+use crate::rules::{RuleSet, Strengths};
+
+/// Pseudo-state for the start of a chart, or of a composite state
+const INITIAL: &str = "[*]";
+
+/// The transitions of a state chart, between state names
+#[derive(Debug, Default)]
+struct Chart {
+    transitions: BTreeSet<(String, String)>,
+    /// The state each composite state starts in
+    initial: BTreeMap<String, String>,
+}
+
+/// Parse the name of a state declared with `state`, and whether it has
+/// substates
+///
+/// This handles `state Name {`, `state "label" as Name {` and
+/// `state Name as "label" {`.
+fn declared_state(line: &str) -> Option<(&str, bool)> {
+    let rest = line.strip_prefix("state ")?;
+    let (rest, composite) = match rest.strip_suffix('{') {
+        Some(rest) => (rest.trim(), true),
+        None => (rest, false),
+    };
+    let rest = rest.split("<<").next().unwrap().trim();
+    let name = match rest.split_once(" as ") {
+        Some((left, right)) if left.starts_with('"') => right.trim(),
+        Some((left, _)) => left.trim(),
+        None => rest,
+    };
+    Some((name, composite))
+}
+
+impl Chart {
+    fn parse(text: &str) -> Chart {
+        let mut chart = Chart::default();
+        // Composite states we are in
+        let mut stack: Vec<String> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line == "}" {
+                stack.pop();
+                continue;
+            }
+            if let Some((name, composite)) = declared_state(line) {
+                if composite {
+                    stack.push(name.to_owned());
+                }
+                continue;
+            }
+
+            // Transitions look like `from --> to : label`, and the arrow can
+            // have a direction such as `-left->`
+            let arrow = line.split(" : ").next().unwrap();
+            let words: Vec<&str> = arrow.split_whitespace().collect();
+            let [from, arrow, to] = words[..] else {
+                continue;
+            };
+            if !arrow.starts_with('-') || !arrow.ends_with("->") {
+                continue;
+            }
+            if from == INITIAL {
+                let parent = stack.last().cloned().unwrap_or_default();
+                chart.initial.insert(parent, to.to_owned());
+            }
+            chart.transitions.insert((from.to_owned(), to.to_owned()));
+        }
+
+        // Going to a composite state goes to the state it starts in
+        chart.transitions = chart.transitions.iter()
+            .map(|(from, to)| match chart.initial.get(to) {
+                Some(initial) => (from.clone(), initial.clone()),
+                None => (from.clone(), to.clone()),
+            })
+            .collect();
+        chart
+    }
+}
+
+/// Play a random game, recording the phase transitions
+fn record_game(seed: u64, transitions: &mut BTreeSet<(String, String)>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (library0, hand0) = deal(0, &mut rng);
+    let (library1, hand1) = deal(1, &mut rng);
+    let mut game = Game::new([Player::new(library0, hand0), Player::new(library1, hand1)]);
+    game.start();
+    transitions.insert((INITIAL.to_owned(), format!("{:?}", game.phase)));
+    for _ in 0..1000 {
+        if game.outcome().is_some() {
+            break;
+        }
+        let player = game.priority();
+        let actions = game.legal_actions(player);
+        let action = actions[rng.gen_range(0..actions.len())].clone();
+        let before = game.phase;
+        game.apply(player, action).unwrap();
+        transitions.insert((format!("{:?}", before), format!("{:?}", game.phase)));
+    }
+}
+
+/// The state of the top-level chart a player is in while picking
+fn pick_state(picks: &PickCards, player: u32) -> String {
+    let name = if picks.is_ready(player) { "Picked" } else { "FirstPick" };
+    format!("Player{}{}", player + 1, name)
+}
+
+/// The state of the top-level chart a started game is in
+fn game_state(game: &Game) -> &'static str {
+    match game.outcome() {
+        None => "Playing",
+        // The chart's only way out, `[one player's life <= 0]`
+        Some(_) if game.players.iter().any(|p| p.life <= 0) => INITIAL,
+        Some(_) => "Decked",
+    }
+}
+
+/// Pick starting hands and play a random game, recording the transitions of
+/// the top-level chart
+fn record_top_level(seed: u64, transitions: &mut BTreeSet<(String, String)>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let decks = [DeckList::standard(), DeckList::standard()];
+    // Games have to end by combat, which deals no damage by default
+    let rules = RuleSet {
+        strengths: Some(Strengths { jack: 2, queen: 4, king: 5, ace: 3 }),
+        ..RuleSet::default()
+    };
+    let hand_size = rules.hand_size;
+    let mut picks = PickCards::new(&decks, rules, &mut rng);
+    let mut add = |from: &str, to: &str| transitions.insert((from.to_owned(), to.to_owned()));
+    add(INITIAL, "PickCards");
+    // `/ pick 5 random cards`
+    for player in 0..2 {
+        if picks.hand(player).len() == hand_size {
+            add("PickCards", &pick_state(&picks, player));
+            add(INITIAL, &pick_state(&picks, player));
+        }
+    }
+
+    // Players pick in a random order, the game can't start before both are
+    // ready
+    while picks.start().is_err() {
+        let player = rng.gen_range(0..2);
+        if picks.is_ready(player) {
+            continue;
+        }
+        let before = pick_state(&picks, player);
+        if rng.gen_bool(0.5) {
+            picks.mulligan(player, &mut rng).unwrap();
+        } else {
+            picks.accept(player).unwrap();
+        }
+        add(&before, &pick_state(&picks, player));
+    }
+    let mut game = picks.start().unwrap();
+    for player in 0..2 {
+        add(&pick_state(&picks, player), "CardsPicked");
+    }
+
+    game.start();
+    let mut state = "CardsPicked";
+    for _ in 0..5000 {
+        if game_state(&game) != state {
+            add(state, game_state(&game));
+            state = game_state(&game);
+        }
+        if game.outcome().is_some() {
+            break;
+        }
+        let player = game.priority();
+        let actions = game.legal_actions(player);
+        let action = actions[rng.gen_range(0..actions.len())].clone();
+        game.apply(player, action).unwrap();
+    }
+}
+
+/// Fail on transitions the engine took that are not in the chart, and report
+/// the ones of the chart never taken
+fn compare(file: &str, chart: &Chart, taken: &BTreeSet<(String, String)>) {
+    let unknown: Vec<_> = taken.difference(&chart.transitions).collect();
+    assert!(unknown.is_empty(), "Transitions not in formal-rules/{}: {:?}", file, unknown);
+    for (from, to) in chart.transitions.difference(taken) {
+        eprintln!("Transition of formal-rules/{} never taken: {} --> {}", file, from, to);
+    }
+}
+
+#[test]
+fn test_parse() {
+    let chart = Chart::parse(
+        "@startuml\n\
+         state Outer {\n\
+         \x20 [*] --> A : / setup\n\
+         \x20 state \"long name\" as B <<exitpoint>>\n\
+         \x20 A -left-> B : go\n\
+         \x20 A : description\n\
+         \x20 B --> Outer\n\
+         }\n\
+         Outer --> [*]\n\
+         @enduml\n",
+    );
+    let transitions: Vec<(&str, &str)> = chart.transitions.iter()
+        .map(|(from, to)| (from.as_str(), to.as_str()))
+        .collect();
+    assert_eq!(transitions, [("A", "B"), ("B", "A"), ("Outer", "[*]"), ("[*]", "A")]);
+}
+
+#[test]
+fn test_playing() {
+    let chart = Chart::parse(include_str!("../formal-rules/playing.uml"));
+    let mut taken = BTreeSet::new();
+    for seed in 0..50 {
+        record_game(seed, &mut taken);
+    }
+
+    compare("playing.uml", &chart, &taken);
+}
+
+#[test]
+fn test_top_level() {
+    let chart = Chart::parse(include_str!("../formal-rules/top-level.uml"));
+    let mut taken = BTreeSet::new();
+    for seed in 0..50 {
+        record_top_level(seed, &mut taken);
+    }
+    compare("top-level.uml", &chart, &taken);
+    // Every transition is taken, picks are quick to cover and random games
+    // end by combat
+    assert!(chart.transitions.is_subset(&taken), "{:?}", chart.transitions.difference(&taken).collect::<Vec<_>>());
+}
+
+/// The instant action charts are not checked, this fails once the engine
+/// gets instant actions, so they can be
+#[test]
+fn test_no_instant_actions() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let (library0, hand0) = deal(0, &mut rng);
+    let (library1, hand1) = deal(1, &mut rng);
+    let mut game = Game::new([Player::new(library0, hand0), Player::new(library1, hand1)]);
+    game.start();
+    for _ in 0..2000 {
+        if game.outcome().is_some() {
+            break;
+        }
+        let player = game.priority();
+        let actions = game.legal_actions(player);
+        if matches!(game.phase, Phase::AttackInstant | Phase::DefendInstant | Phase::FinalInstant) {
+            assert_eq!(actions, [Action::Pass], "instant actions in {:?}, check formal-rules/instant.uml", game.phase);
+        }
+        let action = actions[rng.gen_range(0..actions.len())].clone();
+        game.apply(player, action).unwrap();
+    }
+}
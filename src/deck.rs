@@ -0,0 +1,257 @@
+//! Deck lists, and the rules for building a deck
+//!
+//! A deck list is a text file with one card per line, optionally preceded by
+//! the number of copies. Suits can be written as symbols or letters, and
+//! everything after a `#` is a comment:
+//!
+//! ```text
+//! # Royal hearts
+//! 2 J♥
+//! 2 QH
+//! 3s
+//! ```
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::game::{Card, Face, Suit};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckList {
+    pub cards: Vec<(Face, Suit)>,
+}
+
+/// Construction rules that a deck has to follow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeckRules {
+    /// Number of cards in the deck
+    pub size: usize,
+    /// Maximum number of copies of the same card
+    pub max_copies: usize,
+    /// Suits that can be used
+    pub suits: Vec<Suit>,
+}
+
+impl Default for DeckRules {
+    /// Decks of 40 cards, taken from two regular decks of cards (so no more
+    /// than 2 copies of a card)
+    fn default() -> DeckRules {
+        DeckRules {
+            size: 40,
+            max_copies: 2,
+            suits: Suit::all().to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeckError {
+    Syntax { line: usize, text: String },
+    /// The deck list goes over the deck size at this line
+    TooManyCards { line: usize, max: usize },
+    WrongSize { size: usize, expected: usize },
+    TooManyCopies { face: Face, suit: Suit, copies: usize, max: usize },
+    SuitNotAllowed(Suit),
+}
+
+impl std::fmt::Display for DeckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            DeckError::Syntax { line, ref text } => {
+                write!(f, "Invalid card on line {}: {}", line, text)
+            }
+            DeckError::TooManyCards { line, max } => {
+                write!(f, "Too many cards on line {}, a deck has {} cards", line, max)
+            }
+            DeckError::WrongSize { size, expected } => {
+                write!(f, "Deck has {} cards, it should have {}", size, expected)
+            }
+            DeckError::TooManyCopies { face, suit, copies, max } => write!(
+                f, "Deck has {} copies of {}, the maximum is {}",
+                copies, Card { player: 0, suit, face }, max,
+            ),
+            DeckError::SuitNotAllowed(suit) => {
+                write!(f, "{:?} are not allowed in this deck", suit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeckError {}
+
+fn parse_face(text: &str) -> Option<Face> {
+    Some(match text.to_ascii_uppercase().as_str() {
+        "2" => Face::Two,
+        "3" => Face::Three,
+        "4" => Face::Four,
+        "5" => Face::Five,
+        "6" => Face::Six,
+        "7" => Face::Seven,
+        "J" => Face::Jack,
+        "Q" => Face::Queen,
+        "K" => Face::King,
+        "A" => Face::Ace,
+        _ => return None,
+    })
+}
+
+fn parse_suit(text: &str) -> Option<Suit> {
+    Some(match text {
+        "S" | "s" | "\u{2660}" => Suit::Spades,
+        "H" | "h" | "\u{2665}" => Suit::Hearts,
+        "D" | "d" | "\u{2666}" => Suit::Diamonds,
+        "C" | "c" | "\u{2663}" => Suit::Clubs,
+        _ => return None,
+    })
+}
+
+/// Parse a line such as `2 J♥`, returning the count and the card
+fn parse_line(line: &str) -> Option<(usize, Face, Suit)> {
+    let mut words = line.split_whitespace();
+    let first = words.next()?;
+    let (count, card) = match words.next() {
+        Some(card) => (first.parse().ok()?, card),
+        None => (1, first),
+    };
+    if words.next().is_some() {
+        return None;
+    }
+    let (face, suit) = parse_card(card)?;
+    Some((count, face, suit))
+}
+
+/// Parse a card such as `J♥` or `QH`
+pub fn parse_card(card: &str) -> Option<(Face, Suit)> {
+    let split = card.char_indices().nth(1)?.0;
+    Some((parse_face(&card[..split])?, parse_suit(&card[split..])?))
+}
+
+impl DeckList {
+    /// The deck everyone used before deck building: one of each card
+    pub fn standard() -> DeckList {
+        let mut cards = Vec::with_capacity(40);
+        for &suit in Suit::all() {
+            for &face in Face::all() {
+                cards.push((face, suit));
+            }
+        }
+        DeckList { cards }
+    }
+
+    /// Parse a deck list, stopping if it has more cards than the rules allow
+    pub fn parse(text: &str, rules: &DeckRules) -> Result<DeckList, DeckError> {
+        let mut cards = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(pos) => &line[..pos],
+                None => line,
+            }.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (count, face, suit) = parse_line(line).ok_or_else(|| DeckError::Syntax {
+                line: number + 1,
+                text: line.to_owned(),
+            })?;
+            if count > rules.size - cards.len() {
+                return Err(DeckError::TooManyCards { line: number + 1, max: rules.size });
+            }
+            cards.extend(std::iter::repeat_n((face, suit), count));
+        }
+        Ok(DeckList { cards })
+    }
+
+    /// Check that the deck follows the construction rules
+    pub fn validate(&self, rules: &DeckRules) -> Result<(), DeckError> {
+        if self.cards.len() != rules.size {
+            return Err(DeckError::WrongSize { size: self.cards.len(), expected: rules.size });
+        }
+        let mut copies = BTreeMap::new();
+        for &(face, suit) in &self.cards {
+            if !rules.suits.contains(&suit) {
+                return Err(DeckError::SuitNotAllowed(suit));
+            }
+            *copies.entry((face, suit)).or_insert(0) += 1;
+        }
+        for ((face, suit), copies) in copies {
+            if copies > rules.max_copies {
+                return Err(DeckError::TooManyCopies { face, suit, copies, max: rules.max_copies });
+            }
+        }
+        Ok(())
+    }
+
+    /// Make the cards for a player and shuffle them
+    pub fn shuffle<R: Rng>(&self, player: u32, rng: &mut R) -> Vec<Card> {
+        let mut deck: Vec<Card> = self.cards.iter()
+            .map(|&(face, suit)| Card { player, suit, face })
+            .collect();
+        rand::seq::SliceRandom::shuffle(&mut deck[..], rng);
+        deck
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Face, Suit};
+
+    use super::{DeckError, DeckList, DeckRules};
+
+    #[test]
+    fn test_parse() {
+        let rules = DeckRules::default();
+        let deck = DeckList::parse("# My deck\n2 J\u{2665}\n\n  qs # the queen\n3 2c\n", &rules).unwrap();
+        assert_eq!(deck.cards, vec![
+            (Face::Jack, Suit::Hearts),
+            (Face::Jack, Suit::Hearts),
+            (Face::Queen, Suit::Spades),
+            (Face::Two, Suit::Clubs),
+            (Face::Two, Suit::Clubs),
+            (Face::Two, Suit::Clubs),
+        ]);
+        assert_eq!(
+            DeckList::parse("J\u{2665}\n8\u{2665}\n", &rules),
+            Err(DeckError::Syntax { line: 2, text: "8\u{2665}".to_owned() }),
+        );
+        assert!(DeckList::parse("two JH", &rules).is_err());
+        assert!(DeckList::parse("2 JH QH", &rules).is_err());
+
+        // Huge counts are refused before making the cards
+        assert_eq!(
+            DeckList::parse(&format!("QS\n{} JH\n", usize::MAX), &rules),
+            Err(DeckError::TooManyCards { line: 2, max: 40 }),
+        );
+        assert_eq!(
+            DeckList::parse("40 JH\nQS\n", &rules),
+            Err(DeckError::TooManyCards { line: 2, max: 40 }),
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let rules = DeckRules::default();
+        assert_eq!(DeckList::standard().validate(&rules), Ok(()));
+
+        // Three copies of a card can't come from two decks
+        let mut deck = DeckList::standard();
+        deck.cards[0] = deck.cards[1];
+        deck.cards[2] = deck.cards[1];
+        assert!(matches!(
+            deck.validate(&rules),
+            Err(DeckError::TooManyCopies { copies: 3, max: 2, .. }),
+        ));
+        deck.cards.pop();
+        assert_eq!(deck.validate(&rules), Err(DeckError::WrongSize { size: 39, expected: 40 }));
+
+        // Red-only decks
+        let rules = DeckRules { suits: vec![Suit::Hearts, Suit::Diamonds], ..DeckRules::default() };
+        assert_eq!(DeckList::standard().validate(&rules), Err(DeckError::SuitNotAllowed(Suit::Spades)));
+        let red = DeckList::standard().cards.iter()
+            .filter(|c| rules.suits.contains(&c.1))
+            .flat_map(|&c| [c, c])
+            .collect();
+        assert_eq!(DeckList { cards: red }.validate(&rules), Ok(()));
+    }
+}
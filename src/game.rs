@@ -0,0 +1,1515 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::deck::DeckList;
+use crate::pay::{self, Preferences};
+use crate::rules::{Decking, RuleSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Face {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Jack,
+    Queen,
+    King,
+    Ace,
+}
+
+impl Face {
+    pub fn all() -> &'static [Face] {
+        &[
+            Face::Two,
+            Face::Three,
+            Face::Four,
+            Face::Five,
+            Face::Six,
+            Face::Seven,
+            Face::Jack,
+            Face::Queen,
+            Face::King,
+            Face::Ace,
+        ]
+    }
+
+    pub fn next(&self) -> Option<Face> {
+        match *self {
+            Face::Two => Some(Face::Three),
+            Face::Three => Some(Face::Four),
+            Face::Four => Some(Face::Five),
+            Face::Five => Some(Face::Six),
+            Face::Six => Some(Face::Seven),
+            Face::Seven => None,
+            _ => None,
+        }
+    }
+
+    /// Whether this card is played as a gem
+    pub fn is_gem(&self) -> bool {
+        !matches!(*self, Face::Jack | Face::Queen | Face::King | Face::Ace)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Suit {
+    Spades,
+    Hearts,
+    Diamonds,
+    Clubs,
+}
+
+impl Suit {
+    pub fn all() -> &'static [Suit] {
+        &[Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Color {
+    Red,
+    Black,
+}
+
+impl Color {
+    pub fn all() -> &'static [Color] {
+        &[Color::Red, Color::Black]
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Color::Red => write!(f, "red"),
+            Color::Black => write!(f, "black"),
+        }
+    }
+}
+
+impl Suit {
+    pub fn color(&self) -> Color {
+        match *self {
+            Suit::Spades | Suit::Clubs => Color::Black,
+            Suit::Hearts | Suit::Diamonds => Color::Red,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Card {
+    pub player: u32,
+    pub suit: Suit,
+    pub face: Face,
+}
+
+impl Card {
+    pub fn color(&self) -> Color {
+        self.suit.color()
+    }
+}
+
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let face = match self.face {
+            Face::Two => "2",
+            Face::Three => "3",
+            Face::Four => "4",
+            Face::Five => "5",
+            Face::Six => "6",
+            Face::Seven => "7",
+            Face::Jack => "J",
+            Face::Queen => "Q",
+            Face::King => "K",
+            Face::Ace => "A",
+        };
+        let suit = match self.suit {
+            Suit::Spades => "\u{2660}",
+            Suit::Hearts => "\u{2665}",
+            Suit::Diamonds => "\u{2666}",
+            Suit::Clubs => "\u{2663}",
+        };
+        write!(f, "{}{}", face, suit)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gem {
+    pub card: Card,
+    pub tapped: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreatureStatus {
+    Ready,
+    Tapped,
+    Untrained,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Creature {
+    pub cards: Vec<Card>,
+    pub royal_charge: bool,
+    pub status: CreatureStatus,
+    pub equipment: Vec<[Card; 2]>,
+}
+
+impl Creature {
+    /// Damage dealt in combat, which is also the damage it takes to destroy
+    /// it, or `None` if combat deals no damage with these rules
+    pub fn strength(&self, rules: &RuleSet) -> Option<i32> {
+        self.cards.iter().map(|c| rules.strength(c.face)).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub library: Vec<Card>,
+    pub graveyard: Vec<Card>,
+    pub hand: Vec<Card>,
+    pub life: i32,
+    pub gems: Vec<Gem>,
+    pub creatures: Vec<Creature>,
+    /// Number of gems played this turn
+    pub gems_played: u32,
+    /// Whether the player had to draw from an empty library, and lost
+    pub decked: bool,
+}
+
+impl Player {
+    /// Make a player with the default starting life
+    ///
+    /// The life is changed to the rules' if the game uses different ones.
+    pub fn new(library: Vec<Card>, hand: Vec<Card>) -> Player {
+        Player {
+            library,
+            graveyard: Vec::new(),
+            hand,
+            life: RuleSet::default().starting_life,
+            gems: Vec::new(),
+            creatures: Vec::new(),
+            gems_played: 0,
+            decked: false,
+        }
+    }
+
+    fn has_lost(&self) -> bool {
+        self.life <= 0 || self.decked
+    }
+
+    /// Add card, keeping the hand sorted
+    pub fn add_card(&mut self, card: Card) {
+        let card_key = |c: &Card| (
+            c.color(),
+            c.face,
+            c.suit,
+        );
+        let pos = self.hand.partition_point(|c| card_key(c) < card_key(&card));
+        self.hand.insert(pos, card);
+    }
+
+    /// Add gem, keeping them sorted
+    pub fn add_gem(&mut self, card: Card) {
+        let gem_key = |c: &Card| (
+            c.color(),
+            c.face,
+            c.suit,
+        );
+        let pos = self.gems.partition_point(|c| gem_key(&c.card) < gem_key(&card));
+        self.gems.insert(pos, Gem { card, tapped: false });
+    }
+
+    /// Add creature, keeping them sorted
+    pub fn add_creature(&mut self, creature: Creature) {
+        let creature_key = |c: &Creature| (
+            c.cards[0].face,
+            c.cards[0].color(),
+            c.cards[0].suit,
+        );
+        let pos = self.creatures.partition_point(|c| creature_key(c) < creature_key(&creature));
+        self.creatures.insert(pos, creature);
+    }
+
+    fn reset(&mut self) {
+        for gem in &mut self.gems {
+            gem.tapped = false;
+        }
+        for creature in &mut self.creatures {
+            creature.status = CreatureStatus::Ready;
+        }
+        self.gems_played = 0;
+    }
+}
+
+pub fn random_deck<R: Rng>(player: u32, rng: &mut R) -> Vec<Card> {
+    DeckList::standard().shuffle(player, rng)
+}
+
+pub fn sort_hand(hand: &mut [Card]) {
+    hand.sort_by_key(|card| (
+        card.color(),
+        card.face,
+        card.suit,
+    ));
+}
+
+/// Make a random deck and draw a starting hand from it
+///
+/// Returns the library and the (sorted) hand.
+pub fn deal<R: Rng>(player: u32, rng: &mut R) -> (Vec<Card>, Vec<Card>) {
+    deal_from(&DeckList::standard(), &RuleSet::default(), player, rng)
+}
+
+/// Shuffle a player's deck and draw a starting hand from it
+///
+/// Returns the library and the (sorted) hand.
+pub fn deal_from<R: Rng>(
+    deck: &DeckList,
+    rules: &RuleSet,
+    player: u32,
+    rng: &mut R,
+) -> (Vec<Card>, Vec<Card>) {
+    let mut deck = deck.shuffle(player, rng);
+    let mut hand: Vec<Card> = deck.drain(deck.len().saturating_sub(rules.hand_size)..).collect();
+    sort_hand(&mut hand);
+    (deck, hand)
+}
+
+pub fn is_straight<I: IntoIterator<Item=Face>>(card_faces: I) -> bool {
+    // This relies on the fact that the hand is sorted by face
+    let mut is_straight = true;
+    let mut prev_face: Option<Face> = None;
+    for face in card_faces {
+        if let Some(prev_face) = prev_face {
+            if Some(face) != prev_face.next() {
+                is_straight = false;
+                break;
+            }
+        }
+        prev_face = Some(face);
+    }
+    is_straight
+}
+
+/// All the ways to pick `k` items, keeping their order
+#[cfg(test)]
+fn combinations(items: &[usize], k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for (i, &first) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], k - 1) {
+            rest.insert(0, first);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+/// A legal way to pay for a card with gems
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    /// Positions of the gems to tap, sorted
+    pub gems: Vec<usize>,
+    /// Whether the gems form a straight paying the exact cost, which gives the
+    /// creature Royal Charge
+    pub royal_charge: bool,
+}
+
+/// Check that gems can pay `cost` for a card of `color`
+///
+/// The gems have to be untapped, of the card's color, and there have to be
+/// exactly `cost` of them. Royal Charge is granted if they form a straight;
+/// a single gem is not a straight.
+pub fn check_payment(
+    gems: &[Gem],
+    picked: &[usize],
+    cost: u32,
+    color: Color,
+) -> Result<Payment, RuleError> {
+    let mut picked = picked.to_vec();
+    picked.sort_unstable();
+    picked.dedup();
+    if picked.len() != cost as usize {
+        return Err(RuleError::WrongGemCount { cost });
+    }
+    for &gem in &picked {
+        match gems.get(gem) {
+            None => return Err(RuleError::NoSuchGem),
+            Some(g) if g.tapped || g.card.color() != color => {
+                return Err(RuleError::GemUnavailable);
+            }
+            Some(_) => {}
+        }
+    }
+    // This relies on the fact that the gems are sorted by face
+    let royal_charge = picked.len() >= 2
+        && is_straight(picked.iter().map(|&g| gems[g].card.face));
+    Ok(Payment { gems: picked, royal_charge })
+}
+
+/// Every legal way to pay `cost` for a card of `color`
+///
+/// There are a lot of them with many gems, this is for checking
+/// `pay::suggest()` and `check_payment()` against.
+#[cfg(test)]
+pub fn legal_payments(gems: &[Gem], cost: u32, color: Color) -> Vec<Payment> {
+    let available: Vec<usize> = gems.iter().enumerate()
+        .filter(|(_, g)| !g.tapped && g.card.color() == color)
+        .map(|(i, _)| i)
+        .collect();
+    combinations(&available, cost as usize).into_iter()
+        .map(|picked| check_payment(gems, &picked, cost, color).unwrap())
+        .collect()
+}
+
+/// The phases of a turn, see `formal-rules/playing.uml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase {
+    Main,
+    Attack,
+    Defend,
+    AttackInstant,
+    DefendInstant,
+    SecondMain,
+    FinalInstant,
+}
+
+impl std::fmt::Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Phase::Main => write!(f, "main phase"),
+            Phase::Attack => write!(f, "attack"),
+            Phase::Defend => write!(f, "defense"),
+            Phase::AttackInstant => write!(f, "attacker's instant actions"),
+            Phase::DefendInstant => write!(f, "defender's instant actions"),
+            Phase::SecondMain => write!(f, "second main phase"),
+            Phase::FinalInstant => write!(f, "end of turn"),
+        }
+    }
+}
+
+/// Something a player does, submitted to `Game::apply()`
+///
+/// Cards, gems and creatures are referred to by their position in the
+/// (sorted) lists of the acting player, or of the enemy for `attacker`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    PlayGem { card: usize },
+    PlayCreature { card: usize, gems: Vec<usize> },
+    Attack { creature: usize },
+    Block { creature: usize, attacker: usize },
+    Pass,
+    /// Take back the last action, if the rules allow it
+    Undo,
+}
+
+/// Something that happened, returned by `Game::apply()`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    TurnStarted { player: u32 },
+    /// The card is `None` when the event is shown to the other player
+    CardDrawn { player: u32, card: Option<Card> },
+    LibraryEmpty { player: u32 },
+    GemPlayed { player: u32, card: Card },
+    CreaturePlayed { player: u32, card: Card, royal_charge: bool },
+    PhaseChanged { phase: Phase },
+    AttackerDeclared { player: u32, creature: usize },
+    BlockerDeclared { player: u32, creature: usize, attacker: usize },
+    PlayerDamaged { player: u32, damage: i32, life: i32 },
+    CreatureDestroyed { player: u32, cards: Vec<Card> },
+    /// The player took back their last action
+    Undone { player: u32 },
+}
+
+/// Viewer number for someone watching the game without playing, who only
+/// sees what is public
+pub const SPECTATOR: u32 = 2;
+
+impl Event {
+    /// Hide the information the given player (or `SPECTATOR`) is not allowed
+    /// to see
+    pub fn redact_for(&self, player: u32) -> Event {
+        match *self {
+            Event::CardDrawn { player: p, card: _ } if p != player => {
+                Event::CardDrawn { player: p, card: None }
+            }
+            ref e => e.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Win(u32),
+    Draw,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Outcome::Draw => write!(f, "It's a draw!"),
+            Outcome::Win(player) => write!(f, "Player {} wins", player + 1),
+        }
+    }
+}
+
+/// Why an action was refused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleError {
+    GameOver,
+    NotYourPriority,
+    WrongPhase,
+    NoSuchCard,
+    NoSuchGem,
+    NoSuchCreature,
+    NotAGem,
+    NotACreature,
+    TooManyGems { limit: u32 },
+    WrongGemCount { cost: u32 },
+    GemUnavailable,
+    CreatureNotReady,
+    NotAttacking,
+    AlreadyBlocked,
+    NoMulliganLeft,
+    AlreadyPicked,
+    NotPicked,
+    NothingToUndo,
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            RuleError::GameOver => write!(f, "The game is over"),
+            RuleError::NotYourPriority => write!(f, "It's not your turn to act"),
+            RuleError::WrongPhase => write!(f, "You can't do that now"),
+            RuleError::NoSuchCard => write!(f, "No such card"),
+            RuleError::NoSuchGem => write!(f, "No such gem"),
+            RuleError::NoSuchCreature => write!(f, "No such creature"),
+            RuleError::NotAGem => write!(f, "This card is not a gem"),
+            RuleError::NotACreature => write!(f, "This card is not a creature"),
+            RuleError::TooManyGems { limit: 1 } => write!(f, "You can only play one gem per turn"),
+            RuleError::TooManyGems { limit } => write!(f, "You can only play {} gems per turn", limit),
+            RuleError::WrongGemCount { cost } => write!(f, "You need to pick {} gems", cost),
+            RuleError::GemUnavailable => write!(f, "Gems must be untapped and of the card's color"),
+            RuleError::CreatureNotReady => write!(f, "This creature is not ready"),
+            RuleError::NotAttacking => write!(f, "This creature is not attacking"),
+            RuleError::AlreadyBlocked => write!(f, "This creature is already blocking or blocked"),
+            RuleError::NoMulliganLeft => write!(f, "You can't Mulligan again"),
+            RuleError::AlreadyPicked => write!(f, "You already picked your starting hand"),
+            RuleError::NotPicked => write!(f, "Both players need to pick their starting hand"),
+            RuleError::NothingToUndo => write!(f, "There is nothing to undo"),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Per-player snapshot of the game, without the information that player is
+/// not allowed to see
+///
+/// Only the number of cards is known for libraries and the enemy's hand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub player: u32,
+    pub current_player: u32,
+    /// The player who is expected to act
+    pub priority: u32,
+    pub phase: Phase,
+    pub life: i32,
+    pub hand: Vec<Card>,
+    pub library: usize,
+    pub graveyard: Vec<Card>,
+    pub gems: Vec<Gem>,
+    pub creatures: Vec<Creature>,
+    /// Number of gems played this turn
+    pub gems_played: u32,
+    pub enemy_life: i32,
+    pub enemy_hand: usize,
+    pub enemy_library: usize,
+    pub enemy_graveyard: Vec<Card>,
+    pub enemy_gems: Vec<Gem>,
+    pub enemy_creatures: Vec<Creature>,
+    /// Attacking creatures, belonging to the current player
+    pub attackers: Vec<usize>,
+    /// Blocking creatures and the attacker they block
+    pub blockers: Vec<(usize, usize)>,
+    pub rules: RuleSet,
+}
+
+/// What someone watching the game can see: everything but the hands and
+/// libraries, of which only the number of cards is known
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicView {
+    pub current_player: u32,
+    /// The player who is expected to act
+    pub priority: u32,
+    pub phase: Phase,
+    pub players: [PublicPlayer; 2],
+    /// Attacking creatures, belonging to the current player
+    pub attackers: Vec<usize>,
+    /// Blocking creatures and the attacker they block
+    pub blockers: Vec<(usize, usize)>,
+    pub rules: RuleSet,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicPlayer {
+    pub life: i32,
+    pub hand: usize,
+    pub library: usize,
+    pub graveyard: Vec<Card>,
+    pub gems: Vec<Gem>,
+    pub creatures: Vec<Creature>,
+}
+
+/// A player's starting hand, while they pick it
+#[derive(Debug, Clone)]
+struct Pick {
+    library: Vec<Card>,
+    hand: Vec<Card>,
+    mulligans_left: u32,
+    ready: bool,
+}
+
+/// Picking the starting hands, before the game starts
+///
+/// This is the `PickCards` fork of the top-level state chart: each player
+/// picks on their own, in any order, and the game starts when both are
+/// ready.
+#[derive(Debug, Clone)]
+pub struct PickCards {
+    picks: [Pick; 2],
+    rules: RuleSet,
+}
+
+impl PickCards {
+    /// Shuffle each player's deck and draw their starting hand
+    pub fn new<R: Rng>(decks: &[DeckList; 2], rules: RuleSet, rng: &mut R) -> PickCards {
+        let mut player = 0;
+        let picks = decks.each_ref().map(|deck| {
+            let (library, hand) = deal_from(deck, &rules, player, rng);
+            player += 1;
+            Pick { library, hand, mulligans_left: rules.mulligans, ready: false }
+        });
+        PickCards { picks, rules }
+    }
+
+    pub fn hand(&self, player: u32) -> &[Card] {
+        &self.picks[player as usize].hand
+    }
+
+    pub fn can_mulligan(&self, player: u32) -> bool {
+        let pick = &self.picks[player as usize];
+        !pick.ready && pick.mulligans_left > 0
+    }
+
+    pub fn is_ready(&self, player: u32) -> bool {
+        self.picks[player as usize].ready
+    }
+
+    /// Shuffle the hand back into the library and draw a new one
+    ///
+    /// The player is ready once they have no Mulligan left.
+    pub fn mulligan<R: Rng>(&mut self, player: u32, rng: &mut R) -> Result<&[Card], RuleError> {
+        let pick = &mut self.picks[player as usize];
+        if pick.ready {
+            return Err(RuleError::AlreadyPicked);
+        }
+        if pick.mulligans_left == 0 {
+            return Err(RuleError::NoMulliganLeft);
+        }
+        pick.library.append(&mut pick.hand);
+        rand::seq::SliceRandom::shuffle(&mut pick.library[..], rng);
+        let size = pick.library.len();
+        pick.hand = pick.library.drain(size.saturating_sub(self.rules.hand_size)..).collect();
+        sort_hand(&mut pick.hand);
+        pick.mulligans_left -= 1;
+        pick.ready = pick.mulligans_left == 0;
+        Ok(&pick.hand)
+    }
+
+    /// Keep the current hand
+    pub fn accept(&mut self, player: u32) -> Result<(), RuleError> {
+        let pick = &mut self.picks[player as usize];
+        if pick.ready {
+            return Err(RuleError::AlreadyPicked);
+        }
+        pick.ready = true;
+        Ok(())
+    }
+
+    /// What the player can see while picking, their hand and the size of
+    /// everything else
+    pub fn view_for(&self, player: u32) -> PlayerView {
+        self.game().view_for(player)
+    }
+
+    /// Make the game, once both players are ready
+    pub fn start(&self) -> Result<Game, RuleError> {
+        if !self.picks.iter().all(|p| p.ready) {
+            return Err(RuleError::NotPicked);
+        }
+        Ok(self.game())
+    }
+
+    fn game(&self) -> Game {
+        let players = self.picks.each_ref().map(|p| Player::new(p.library.clone(), p.hand.clone()));
+        Game::with_rules(players, self.rules.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub current_player: u32,
+    pub players: [Player; 2],
+    pub phase: Phase,
+    /// Attacking creatures, belonging to the current player
+    pub attackers: Vec<usize>,
+    /// Blocking creatures (belonging to the enemy) and the attacker they block
+    pub blockers: Vec<(usize, usize)>,
+    pub rules: RuleSet,
+    /// States before each action of the player with priority, if the rules
+    /// allow undoing
+    undo: Vec<Game>,
+}
+
+impl Game {
+    pub fn new(players: [Player; 2]) -> Game {
+        Game::with_rules(players, RuleSet::default())
+    }
+
+    pub fn with_rules(mut players: [Player; 2], rules: RuleSet) -> Game {
+        for player in &mut players {
+            player.life = rules.starting_life;
+        }
+        Game {
+            current_player: 0,
+            players,
+            phase: Phase::Main,
+            attackers: Vec::new(),
+            blockers: Vec::new(),
+            rules,
+            undo: Vec::new(),
+        }
+    }
+
+    /// Start the first turn
+    pub fn start(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        self.begin_turn(&mut events);
+        events
+    }
+
+    fn us_mut(&mut self) -> &mut Player {
+        &mut self.players[self.current_player as usize]
+    }
+
+    /// The player who is expected to act
+    pub fn priority(&self) -> u32 {
+        match self.phase {
+            Phase::Defend | Phase::DefendInstant | Phase::FinalInstant => {
+                1 - self.current_player
+            }
+            _ => self.current_player,
+        }
+    }
+
+    /// The result of the game, if it is over
+    pub fn outcome(&self) -> Option<Outcome> {
+        match (self.players[0].has_lost(), self.players[1].has_lost()) {
+            (true, true) => Some(Outcome::Draw),
+            (true, false) => Some(Outcome::Win(1)),
+            (false, true) => Some(Outcome::Win(0)),
+            (false, false) => None,
+        }
+    }
+
+    /// What the given player can see of the game
+    pub fn view_for(&self, player: u32) -> PlayerView {
+        let us = &self.players[player as usize];
+        let enemy = &self.players[(1 - player) as usize];
+        PlayerView {
+            player,
+            current_player: self.current_player,
+            priority: self.priority(),
+            phase: self.phase,
+            life: us.life,
+            hand: us.hand.clone(),
+            library: us.library.len(),
+            graveyard: us.graveyard.clone(),
+            gems: us.gems.clone(),
+            creatures: us.creatures.clone(),
+            gems_played: us.gems_played,
+            enemy_life: enemy.life,
+            enemy_hand: enemy.hand.len(),
+            enemy_library: enemy.library.len(),
+            enemy_graveyard: enemy.graveyard.clone(),
+            enemy_gems: enemy.gems.clone(),
+            enemy_creatures: enemy.creatures.clone(),
+            attackers: self.attackers.clone(),
+            blockers: self.blockers.clone(),
+            rules: self.rules.clone(),
+        }
+    }
+
+    /// What spectators can see of the game
+    pub fn public_view(&self) -> PublicView {
+        PublicView {
+            current_player: self.current_player,
+            priority: self.priority(),
+            phase: self.phase,
+            players: self.players.each_ref().map(|p| PublicPlayer {
+                life: p.life,
+                hand: p.hand.len(),
+                library: p.library.len(),
+                graveyard: p.graveyard.clone(),
+                gems: p.gems.clone(),
+                creatures: p.creatures.clone(),
+            }),
+            attackers: self.attackers.clone(),
+            blockers: self.blockers.clone(),
+            rules: self.rules.clone(),
+        }
+    }
+
+    /// Every action the given player is allowed to take right now
+    ///
+    /// Creatures are played with the suggested payment, and with the best
+    /// payment giving Royal Charge when the suggestion doesn't. Other
+    /// payments are legal as well, `check_payment()` checks them.
+    pub fn legal_actions(&self, player: u32) -> Vec<Action> {
+        if self.outcome().is_some() || player != self.priority() {
+            return Vec::new();
+        }
+        let us = &self.players[player as usize];
+        let mut actions = Vec::new();
+        match self.phase {
+            Phase::Main => {
+                for (card, c) in us.hand.iter().enumerate() {
+                    match self.rules.cost(c.face) {
+                        None => {
+                            if us.gems_played < self.rules.gems_per_turn {
+                                actions.push(Action::PlayGem { card });
+                            }
+                        }
+                        Some(cost) => {
+                            let royal_charge = Preferences {
+                                royal_charge: true,
+                                ..self.rules.payment.clone()
+                            };
+                            let mut payments: Vec<Vec<usize>> = Vec::new();
+                            for preferences in [&self.rules.payment, &royal_charge] {
+                                match pay::suggest(&us.gems, cost, c.color(), preferences) {
+                                    Some(gems) if !payments.contains(&gems) => payments.push(gems),
+                                    _ => {}
+                                }
+                            }
+                            for gems in payments {
+                                actions.push(Action::PlayCreature { card, gems });
+                            }
+                        }
+                    }
+                }
+            }
+            Phase::Attack => {
+                for (creature, c) in us.creatures.iter().enumerate() {
+                    if c.status == CreatureStatus::Ready {
+                        actions.push(Action::Attack { creature });
+                    }
+                }
+            }
+            Phase::Defend => {
+                for (creature, c) in us.creatures.iter().enumerate() {
+                    if c.status == CreatureStatus::Tapped
+                        || self.blockers.iter().any(|&(b, _)| b == creature)
+                    {
+                        continue;
+                    }
+                    for &attacker in &self.attackers {
+                        if !self.blockers.iter().any(|&(_, a)| a == attacker) {
+                            actions.push(Action::Block { creature, attacker });
+                        }
+                    }
+                }
+            }
+            // TODO: Instant actions
+            Phase::AttackInstant | Phase::DefendInstant
+            | Phase::SecondMain | Phase::FinalInstant => {}
+        }
+        actions.push(Action::Pass);
+        if !self.undo.is_empty() {
+            actions.push(Action::Undo);
+        }
+        actions
+    }
+
+    fn begin_turn(&mut self, events: &mut Vec<Event>) {
+        let player = self.current_player;
+        events.push(Event::TurnStarted { player });
+
+        // Draw a card
+        match self.us_mut().library.pop() {
+            Some(card) => {
+                events.push(Event::CardDrawn { player, card: Some(card.clone()) });
+                self.us_mut().add_card(card);
+            }
+            None => {
+                events.push(Event::LibraryEmpty { player });
+                if self.rules.decking == Decking::Lose {
+                    self.us_mut().decked = true;
+                }
+            }
+        }
+
+        // Reset everything
+        self.us_mut().reset();
+        self.attackers.clear();
+        self.blockers.clear();
+        self.phase = Phase::Main;
+    }
+
+    /// Check whether a player is allowed to perform an action, without
+    /// performing it
+    pub fn check(&self, player: u32, action: &Action) -> Result<(), RuleError> {
+        if self.outcome().is_some() {
+            return Err(RuleError::GameOver);
+        }
+        if player != self.priority() {
+            return Err(RuleError::NotYourPriority);
+        }
+        let us = &self.players[player as usize];
+        match *action {
+            Action::PlayGem { card } => {
+                if self.phase != Phase::Main {
+                    return Err(RuleError::WrongPhase);
+                }
+                match us.hand.get(card) {
+                    None => return Err(RuleError::NoSuchCard),
+                    Some(c) if !c.face.is_gem() => return Err(RuleError::NotAGem),
+                    Some(_) => {}
+                }
+                if us.gems_played >= self.rules.gems_per_turn {
+                    return Err(RuleError::TooManyGems { limit: self.rules.gems_per_turn });
+                }
+            }
+            Action::PlayCreature { card, ref gems } => {
+                if self.phase != Phase::Main {
+                    return Err(RuleError::WrongPhase);
+                }
+                let (cost, color) = match us.hand.get(card) {
+                    None => return Err(RuleError::NoSuchCard),
+                    Some(c) => match self.rules.cost(c.face) {
+                        None => return Err(RuleError::NotACreature),
+                        Some(cost) => (cost, c.color()),
+                    },
+                };
+                check_payment(&us.gems, gems, cost, color)?;
+            }
+            Action::Attack { creature } => {
+                if self.phase != Phase::Attack {
+                    return Err(RuleError::WrongPhase);
+                }
+                match us.creatures.get(creature) {
+                    None => return Err(RuleError::NoSuchCreature),
+                    Some(c) if c.status != CreatureStatus::Ready => {
+                        return Err(RuleError::CreatureNotReady);
+                    }
+                    Some(_) => {}
+                }
+            }
+            Action::Block { creature, attacker } => {
+                if self.phase != Phase::Defend {
+                    return Err(RuleError::WrongPhase);
+                }
+                match us.creatures.get(creature) {
+                    None => return Err(RuleError::NoSuchCreature),
+                    Some(c) if c.status == CreatureStatus::Tapped => {
+                        return Err(RuleError::CreatureNotReady);
+                    }
+                    Some(_) => {}
+                }
+                if !self.attackers.contains(&attacker) {
+                    return Err(RuleError::NotAttacking);
+                }
+                if self.blockers.iter().any(|&(b, a)| b == creature || a == attacker) {
+                    return Err(RuleError::AlreadyBlocked);
+                }
+            }
+            Action::Undo => {
+                if self.undo.is_empty() {
+                    return Err(RuleError::NothingToUndo);
+                }
+            }
+            Action::Pass => {}
+        }
+        Ok(())
+    }
+
+    /// Have a player perform an action
+    ///
+    /// Nothing is changed if the action is not allowed.
+    pub fn apply(&mut self, player: u32, action: Action) -> Result<Vec<Event>, RuleError> {
+        self.check(player, &action)?;
+        if action == Action::Undo {
+            let mut previous = self.undo.pop().unwrap();
+            previous.undo = std::mem::take(&mut self.undo);
+            *self = previous;
+            return Ok(vec![Event::Undone { player }]);
+        }
+
+        // Keep the current state around, in case the player wants it back
+        let undo = std::mem::take(&mut self.undo);
+        let before = if self.rules.undo { Some(self.clone()) } else { None };
+        let passing = action == Action::Pass;
+        let events = self.apply_action(player, action);
+        self.undo = undo;
+
+        // Actions can't be taken back once the other player has priority, or
+        // a card was drawn
+        let drawn = events.iter().any(|e| matches!(e, Event::CardDrawn { .. } | Event::LibraryEmpty { .. }));
+        if passing || drawn {
+            self.undo.clear();
+        } else if let Some(before) = before {
+            self.undo.push(before);
+        }
+        Ok(events)
+    }
+
+    /// Perform an action that was checked
+    fn apply_action(&mut self, player: u32, action: Action) -> Vec<Event> {
+        let mut events = Vec::new();
+        match action {
+            Action::PlayGem { card } => {
+                let us = &mut self.players[player as usize];
+                let card = us.hand.remove(card);
+                us.gems_played += 1;
+                events.push(Event::GemPlayed { player, card: card.clone() });
+                us.add_gem(card);
+            }
+            Action::PlayCreature { card, gems } => {
+                let us = &mut self.players[player as usize];
+                let c = &us.hand[card];
+                let cost = self.rules.cost(c.face).unwrap();
+                let Payment { gems, royal_charge } = check_payment(&us.gems, &gems, cost, c.color()).unwrap();
+
+                // Tap selected gems
+                for &gem in &gems {
+                    us.gems[gem].tapped = true;
+                }
+
+                let card = us.hand.remove(card);
+                events.push(Event::CreaturePlayed {
+                    player,
+                    card: card.clone(),
+                    royal_charge,
+                });
+                us.add_creature(Creature {
+                    cards: vec![card],
+                    royal_charge,
+                    status: CreatureStatus::Untrained,
+                    equipment: Vec::new(),
+                });
+            }
+            Action::Attack { creature } => {
+                self.players[player as usize].creatures[creature].status = CreatureStatus::Tapped;
+                self.attackers.push(creature);
+                events.push(Event::AttackerDeclared { player, creature });
+            }
+            Action::Block { creature, attacker } => {
+                self.blockers.push((creature, attacker));
+                events.push(Event::BlockerDeclared { player, creature, attacker });
+            }
+            // Handled by apply()
+            Action::Undo => {}
+            Action::Pass => {
+                match self.phase {
+                    Phase::Main => self.phase = Phase::Attack,
+                    Phase::Attack => {
+                        if self.attackers.is_empty() {
+                            self.phase = Phase::SecondMain;
+                        } else {
+                            self.phase = Phase::Defend;
+                        }
+                    }
+                    Phase::Defend => self.phase = Phase::AttackInstant,
+                    Phase::AttackInstant => self.phase = Phase::DefendInstant,
+                    Phase::DefendInstant => {
+                        self.deal_damage(&mut events);
+                        self.phase = Phase::SecondMain;
+                    }
+                    Phase::SecondMain => self.phase = Phase::FinalInstant,
+                    Phase::FinalInstant => {
+                        // Next turn
+                        self.current_player = 1 - self.current_player;
+                        self.begin_turn(&mut events);
+                        return events;
+                    }
+                }
+                events.push(Event::PhaseChanged { phase: self.phase });
+            }
+        }
+        events
+    }
+
+    /// Resolve combat, if the rules give creatures a strength
+    fn deal_damage(&mut self, events: &mut Vec<Event>) {
+        let attacking = self.current_player;
+        let defending = 1 - attacking;
+        let mut destroyed = [Vec::new(), Vec::new()];
+        // TODO: The rules don't say how much damage creatures deal yet (see
+        // formal-rules/README.md), only house rules do
+        let attackers = match self.rules.strengths {
+            Some(_) => self.attackers.as_slice(),
+            None => &[],
+        };
+        for &attacker in attackers {
+            let strength = self.players[attacking as usize].creatures[attacker]
+                .strength(&self.rules)
+                .unwrap_or(0);
+            match self.blockers.iter().find(|&&(_, a)| a == attacker) {
+                Some(&(blocker, _)) => {
+                    let blocker_strength = self.players[defending as usize].creatures[blocker]
+                        .strength(&self.rules)
+                        .unwrap_or(0);
+                    if strength >= blocker_strength {
+                        destroyed[defending as usize].push(blocker);
+                    }
+                    if blocker_strength >= strength {
+                        destroyed[attacking as usize].push(attacker);
+                    }
+                }
+                None => {
+                    let enemy = &mut self.players[defending as usize];
+                    enemy.life -= strength;
+                    events.push(Event::PlayerDamaged {
+                        player: defending,
+                        damage: strength,
+                        life: enemy.life,
+                    });
+                }
+            }
+        }
+
+        // Remove destroyed creatures, highest positions first
+        for player in [attacking, defending] {
+            let list = &mut destroyed[player as usize];
+            list.sort_unstable();
+            for &creature in list.iter().rev() {
+                let creature = self.players[player as usize].creatures.remove(creature);
+                events.push(Event::CreatureDestroyed {
+                    player,
+                    cards: creature.cards.clone(),
+                });
+                self.discard_creature(creature);
+            }
+        }
+        self.attackers.clear();
+        self.blockers.clear();
+    }
+
+    /// Put the cards of a creature in their owners' graveyards
+    fn discard_creature(&mut self, creature: Creature) {
+        let cards = creature.cards.into_iter()
+            .chain(creature.equipment.into_iter().flatten());
+        for card in cards {
+            self.players[card.player as usize].graveyard.push(card);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::{
+        Action, Card, Color, Event, Face, Game, Outcome, Phase, PickCards,
+        Payment, Player, RuleError, SPECTATOR, Suit, check_payment, deal,
+        is_straight, legal_payments,
+    };
+    use crate::pay::{self, Preferences};
+    use crate::deck::DeckList;
+    use crate::rules::{Costs, Decking, RuleSet};
+
+    fn new_game() -> Game {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (library1, hand1) = deal(0, &mut rng);
+        let (library2, hand2) = deal(1, &mut rng);
+        Game::new([Player::new(library1, hand1), Player::new(library2, hand2)])
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn test_is_straight() {
+        assert_eq!(is_straight([Face::Two]), true);
+        assert_eq!(is_straight([Face::Two, Face::Three]), true);
+        assert_eq!(is_straight([Face::Two, Face::Two]), false);
+        assert_eq!(is_straight([Face::Two, Face::Four]), false);
+        assert_eq!(is_straight([Face::Three, Face::Four, Face::Five]), true);
+        assert_eq!(is_straight([Face::Three, Face::Four, Face::Six]), false);
+        assert_eq!(is_straight([Face::Three, Face::Four, Face::Four]), false);
+        assert_eq!(
+            is_straight([Face::Three, Face::Four, Face::Five, Face::Six]),
+            true,
+        );
+        assert_eq!(
+            is_straight([Face::Three, Face::Four, Face::Five, Face::Seven]),
+            false,
+        );
+        assert_eq!(
+            is_straight([Face::Two, Face::Four, Face::Five, Face::Six]),
+            false,
+        );
+        assert_eq!(
+            is_straight([Face::Three, Face::Four, Face::Four, Face::Five]),
+            false,
+        );
+        assert_eq!(
+            is_straight([Face::Three, Face::Four, Face::Four, Face::Six]),
+            false,
+        );
+    }
+
+    #[test]
+    fn test_turn_phases() {
+        let mut game = new_game();
+        let events = game.start();
+        assert_eq!(events[0], Event::TurnStarted { player: 0 });
+        assert_eq!(game.players[0].hand.len(), 6);
+
+        assert_eq!(game.apply(1, Action::Pass), Err(RuleError::NotYourPriority));
+        game.apply(0, Action::Pass).unwrap();
+        assert_eq!(game.phase, Phase::Attack);
+        // No attackers, skip defense
+        game.apply(0, Action::Pass).unwrap();
+        assert_eq!(game.phase, Phase::SecondMain);
+        game.apply(0, Action::Pass).unwrap();
+        assert_eq!(game.phase, Phase::FinalInstant);
+        assert_eq!(game.priority(), 1);
+        let events = game.apply(1, Action::Pass).unwrap();
+        assert_eq!(events[0], Event::TurnStarted { player: 1 });
+        assert_eq!(game.current_player, 1);
+        assert_eq!(game.phase, Phase::Main);
+    }
+
+    #[test]
+    fn test_legal_actions() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut game = new_game();
+        game.start();
+        for _ in 0..500 {
+            let player = game.priority();
+            assert!(game.legal_actions(1 - player).is_empty());
+            let actions = game.legal_actions(player);
+            for action in &actions {
+                assert!(game.clone().apply(player, action.clone()).is_ok(), "{:?}", action);
+            }
+            let action = actions[rng.gen_range(0..actions.len())].clone();
+            game.apply(player, action).unwrap();
+            if game.outcome().is_some() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_payments() {
+        let mut player = Player::new(Vec::new(), vec![
+            Card { player: 0, suit: Suit::Hearts, face: Face::Jack },
+        ]);
+        for (suit, face) in [
+            (Suit::Hearts, Face::Two),
+            (Suit::Hearts, Face::Three),
+            (Suit::Hearts, Face::Four),
+            (Suit::Hearts, Face::Five),
+            (Suit::Diamonds, Face::Six),
+            (Suit::Hearts, Face::Seven),
+            (Suit::Spades, Face::Two),
+        ] {
+            player.add_gem(Card { player: 0, suit, face });
+        }
+        player.gems[2].tapped = true;
+        let gems = player.gems.clone();
+        let position = |face| Face::all().iter().position(|&f| f == face).unwrap();
+
+        // Try every set of gems, for every cost
+        for cost in 0..=5 {
+            let payments = legal_payments(&gems, cost, Color::Red);
+            let mut legal = 0;
+            for mask in 0..(1 << gems.len()) {
+                let picked: Vec<usize> = (0..gems.len()).filter(|i| mask & (1 << i) != 0).collect();
+                let valid = picked.len() == cost as usize
+                    && picked.iter().all(|&g| !gems[g].tapped && gems[g].card.color() == Color::Red);
+                let royal_charge = valid
+                    && picked.len() >= 2
+                    && picked.windows(2).all(|w| {
+                        position(gems[w[1]].card.face) == position(gems[w[0]].card.face) + 1
+                    });
+
+                let result = check_payment(&gems, &picked, cost, Color::Red);
+                assert_eq!(result.is_ok(), valid, "cost {} gems {:?}", cost, picked);
+                if let Ok(payment) = result {
+                    legal += 1;
+                    assert_eq!(payment.royal_charge, royal_charge, "cost {} gems {:?}", cost, picked);
+                    assert!(payments.contains(&payment));
+                }
+
+                // The game agrees
+                let rules = RuleSet {
+                    costs: Costs { jack: cost, ..Costs::default() },
+                    ..RuleSet::default()
+                };
+                let mut game = Game::with_rules(
+                    [player.clone(), Player::new(Vec::new(), Vec::new())],
+                    rules,
+                );
+                let action = Action::PlayCreature { card: 0, gems: picked.clone() };
+                assert_eq!(game.check(0, &action).is_ok(), valid);
+                match game.apply(0, action) {
+                    Ok(events) => {
+                        assert!(valid);
+                        assert!(matches!(
+                            events[..],
+                            [Event::CreaturePlayed { royal_charge: r, .. }] if r == royal_charge,
+                        ));
+                    }
+                    Err(_) => assert!(!valid),
+                }
+            }
+            assert_eq!(payments.len(), legal);
+
+            // Only the suggested payment is offered, and the best one with
+            // Royal Charge when the suggestion doesn't have it
+            let without = Preferences { royal_charge: false, ..Preferences::default() };
+            for preferences in [Preferences::default(), without] {
+                let rules = RuleSet {
+                    costs: Costs { jack: cost, ..Costs::default() },
+                    payment: preferences.clone(),
+                    ..RuleSet::default()
+                };
+                let game = Game::with_rules(
+                    [player.clone(), Player::new(Vec::new(), Vec::new())],
+                    rules,
+                );
+                let offered: Vec<Payment> = game.legal_actions(0).into_iter()
+                    .filter_map(|action| match action {
+                        Action::PlayCreature { gems: picked, .. } => {
+                            Some(check_payment(&gems, &picked, cost, Color::Red).unwrap())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let suggested = pay::suggest(&gems, cost, Color::Red, &preferences);
+                assert_eq!(offered.first().map(|p| &p.gems), suggested.as_ref());
+                let royal_charge = payments.iter().any(|p| p.royal_charge);
+                if preferences.royal_charge || !royal_charge || offered[0].royal_charge {
+                    assert!(offered.len() <= 1);
+                } else {
+                    assert_eq!(offered.len(), 2);
+                    assert!(offered[1].royal_charge);
+                }
+            }
+        }
+
+        // A single gem is not a straight
+        let rules = RuleSet { costs: Costs { jack: 1, ..Costs::default() }, ..RuleSet::default() };
+        let mut game = Game::with_rules([player, Player::new(Vec::new(), Vec::new())], rules);
+        let events = game.apply(0, Action::PlayCreature { card: 0, gems: vec![0] }).unwrap();
+        assert!(matches!(events[..], [Event::CreaturePlayed { royal_charge: false, .. }]));
+
+        // Errors
+        assert_eq!(
+            check_payment(&gems, &[0, 1, 3], 2, Color::Red),
+            Err(RuleError::WrongGemCount { cost: 2 }),
+        );
+        assert_eq!(check_payment(&gems, &[0, 9], 2, Color::Red), Err(RuleError::NoSuchGem));
+        assert_eq!(check_payment(&gems, &[1, 2], 2, Color::Red), Err(RuleError::GemUnavailable));
+        assert_eq!(check_payment(&gems, &[0, 6], 2, Color::Red), Err(RuleError::GemUnavailable));
+    }
+
+    #[test]
+    fn test_play_cards() {
+        let mut game = new_game();
+        game.start();
+        game.players[0].hand = vec![
+            Card { player: 0, suit: Suit::Hearts, face: Face::Two },
+            Card { player: 0, suit: Suit::Hearts, face: Face::Three },
+            Card { player: 0, suit: Suit::Hearts, face: Face::Jack },
+        ];
+
+        game.apply(0, Action::PlayGem { card: 0 }).unwrap();
+        assert_eq!(
+            game.apply(0, Action::PlayGem { card: 0 }),
+            Err(RuleError::TooManyGems { limit: 1 }),
+        );
+        assert_eq!(
+            game.apply(0, Action::PlayCreature { card: 1, gems: vec![0] }),
+            Err(RuleError::WrongGemCount { cost: 2 }),
+        );
+        game.players[0].gems_played = 0;
+        game.apply(0, Action::PlayGem { card: 0 }).unwrap();
+        let events = game.apply(0, Action::PlayCreature { card: 0, gems: vec![0, 1] }).unwrap();
+        assert_eq!(
+            events,
+            vec![Event::CreaturePlayed {
+                player: 0,
+                card: Card { player: 0, suit: Suit::Hearts, face: Face::Jack },
+                royal_charge: true,
+            }],
+        );
+        assert!(game.players[0].gems.iter().all(|g| g.tapped));
+        assert!(game.players[0].hand.is_empty());
+    }
+
+    #[test]
+    fn test_rule_variants() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (library, _) = deal(0, &mut rng);
+        let rules = RuleSet {
+            starting_life: 5,
+            gems_per_turn: 2,
+            decking: Decking::Lose,
+            ..RuleSet::default()
+        };
+        let gems = vec![
+            Card { player: 0, suit: Suit::Hearts, face: Face::Two },
+            Card { player: 0, suit: Suit::Hearts, face: Face::Three },
+            Card { player: 0, suit: Suit::Hearts, face: Face::Four },
+        ];
+        let mut game = Game::with_rules(
+            [Player::new(library, gems), Player::new(Vec::new(), Vec::new())],
+            rules,
+        );
+        assert_eq!(game.players[1].life, 5);
+        game.start();
+
+        // Two gems per turn
+        game.apply(0, Action::PlayGem { card: 0 }).unwrap();
+        game.apply(0, Action::PlayGem { card: 0 }).unwrap();
+        assert!(!game.legal_actions(0).iter().any(|a| matches!(a, Action::PlayGem { .. })));
+        assert_eq!(
+            game.apply(0, Action::PlayGem { card: 0 }),
+            Err(RuleError::TooManyGems { limit: 2 }),
+        );
+
+        // Player 2 can't draw, and loses
+        for _ in 0..3 {
+            game.apply(0, Action::Pass).unwrap();
+        }
+        let events = game.apply(1, Action::Pass).unwrap();
+        assert!(events.contains(&Event::LibraryEmpty { player: 1 }));
+        assert_eq!(game.outcome(), Some(Outcome::Win(0)));
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (library0, _) = deal(0, &mut rng);
+        let (library1, hand1) = deal(1, &mut rng);
+        let hand0 = vec![
+            Card { player: 0, suit: Suit::Spades, face: Face::Two },
+            Card { player: 0, suit: Suit::Spades, face: Face::Jack },
+        ];
+        let players = [Player::new(library0, hand0), Player::new(library1, hand1)];
+
+        // Not allowed by default
+        let mut game = Game::new(players.clone());
+        game.start();
+        let gem = game.players[0].hand.iter().position(|c| c.face == Face::Two).unwrap();
+        game.apply(0, Action::PlayGem { card: gem }).unwrap();
+        assert!(!game.legal_actions(0).contains(&Action::Undo));
+        assert_eq!(game.apply(0, Action::Undo), Err(RuleError::NothingToUndo));
+
+        let mut game = Game::with_rules(players, RuleSet { undo: true, ..RuleSet::default() });
+        game.start();
+        let hand = game.players[0].hand.clone();
+        let gem = hand.iter().position(|c| c.face == Face::Two).unwrap();
+        assert!(!game.legal_actions(0).contains(&Action::Undo));
+        game.apply(0, Action::PlayGem { card: gem }).unwrap();
+        assert_eq!(game.players[0].gems.len(), 1);
+        assert!(game.legal_actions(0).contains(&Action::Undo));
+        assert_eq!(game.apply(0, Action::Undo), Ok(vec![Event::Undone { player: 0 }]));
+        assert_eq!(game.players[0].hand, hand);
+        assert!(game.players[0].gems.is_empty());
+        assert_eq!(game.apply(0, Action::Undo), Err(RuleError::NothingToUndo));
+
+        // Passing priority can't be taken back
+        game.apply(0, Action::PlayGem { card: gem }).unwrap();
+        game.apply(0, Action::Pass).unwrap();
+        assert!(!game.legal_actions(game.priority()).contains(&Action::Undo));
+        assert_eq!(game.apply(game.priority(), Action::Undo), Err(RuleError::NothingToUndo));
+        assert_eq!(game.players[0].gems.len(), 1);
+    }
+
+    #[test]
+    fn test_pick_cards() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let red = DeckList {
+            cards: DeckList::standard().cards.into_iter()
+                .filter(|&(_, suit)| suit == Suit::Hearts)
+                .collect(),
+        };
+        let rules = RuleSet { mulligans: 2, ..RuleSet::default() };
+        let mut picks = PickCards::new(&[red, DeckList::standard()], rules, &mut rng);
+        assert_eq!(picks.hand(0).len(), 5);
+        // The view shows the player's hand, only the size of the enemy's
+        let view = picks.view_for(0);
+        assert_eq!(view.hand, picks.hand(0));
+        assert_eq!((view.library, view.enemy_hand, view.enemy_library), (5, 5, 35));
+
+        // Players pick in any order, the game only starts when both are done
+        picks.accept(1).unwrap();
+        assert_eq!(picks.accept(1), Err(RuleError::AlreadyPicked));
+        assert_eq!(picks.mulligan(1, &mut rng), Err(RuleError::AlreadyPicked));
+        assert_eq!(picks.start().err(), Some(RuleError::NotPicked));
+
+        // Mulligans reshuffle the player's own deck
+        let first = picks.hand(0).to_vec();
+        picks.mulligan(0, &mut rng).unwrap();
+        assert!(picks.can_mulligan(0));
+        picks.mulligan(0, &mut rng).unwrap();
+        assert_ne!(picks.hand(0), first);
+        assert!(picks.hand(0).iter().all(|c| c.suit == Suit::Hearts && c.player == 0));
+        assert!(picks.is_ready(0));
+        assert_eq!(picks.mulligan(0, &mut rng), Err(RuleError::AlreadyPicked));
+
+        let game = picks.start().unwrap();
+        assert_eq!(game.players[0].hand.len() + game.players[0].library.len(), 10);
+        assert_eq!(game.players[1].library.len(), 35);
+    }
+
+    #[test]
+    fn test_view_for() {
+        let mut game = new_game();
+        game.start();
+        game.players[1].graveyard.push(Card { player: 1, suit: Suit::Clubs, face: Face::King });
+        game.apply(0, Action::Pass).unwrap();
+        game.apply(0, Action::Pass).unwrap();
+        game.apply(0, Action::Pass).unwrap();
+
+        let view = game.view_for(0);
+        assert_eq!(view.priority, 1);
+        assert_eq!(view.hand, game.players[0].hand);
+        assert_eq!(view.library, 34);
+        assert_eq!(view.enemy_hand, 5);
+        assert_eq!(view.enemy_library, 35);
+        assert_eq!(view.enemy_graveyard.len(), 1);
+
+        let view = game.view_for(1);
+        assert_eq!(view.priority, 1);
+        assert_eq!(view.hand, game.players[1].hand);
+        assert_eq!(view.enemy_hand, 6);
+        assert_eq!(view.graveyard.len(), 1);
+
+        // Spectators see neither hand, nor the cards drawn
+        let view = game.public_view();
+        assert_eq!(view.priority, 1);
+        assert_eq!([view.players[0].hand, view.players[1].hand], [6, 5]);
+        assert_eq!(view.players[1].graveyard.len(), 1);
+        let drawn = Event::CardDrawn { player: 0, card: Some(game.players[0].hand[0].clone()) };
+        assert_eq!(drawn.redact_for(SPECTATOR), Event::CardDrawn { player: 0, card: None });
+    }
+}
@@ -0,0 +1,254 @@
+//! What a player can do right now, and why they can't do the rest
+//!
+//! This works from a `PlayerView`, so clients can explain the rules without
+//! access to the whole game. Sacrifices, straights and stacking are listed
+//! too, so players looking for them learn that the engine doesn't support
+//! them yet.
+
+use crate::game::{Color, CreatureStatus, Phase, PlayerView};
+
+/// Something the player might want to do with one of their cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    PlayGem { card: usize },
+    Summon { card: usize },
+    Attack { creature: usize },
+    Block { creature: usize },
+    /// Sacrifice a gem
+    Sacrifice,
+    /// Play a straight from the gems
+    Straight,
+    /// Stack Jacks into one creature
+    Stack,
+    Pass,
+}
+
+/// Why something can't be done
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    NotYourPriority,
+    /// Only possible in that phase
+    WrongPhase { phase: Phase },
+    GemLimit { limit: u32 },
+    NotEnoughGems { cost: u32, color: Color, untapped: u32 },
+    Untrained,
+    Tapped,
+    AlreadyBlocking,
+    AllBlocked,
+    /// Part of the rules, but not of this game yet
+    NotSupported,
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Reason::NotYourPriority => write!(f, "it's not your turn to act"),
+            Reason::WrongPhase { phase: Phase::Defend } => write!(f, "only when the enemy attacks"),
+            Reason::WrongPhase { phase } => write!(f, "only during your {}", phase),
+            Reason::GemLimit { limit: 1 } => write!(f, "you already played a gem this turn"),
+            Reason::GemLimit { limit } => write!(f, "you already played {} gems this turn", limit),
+            Reason::NotEnoughGems { cost, color, untapped } => write!(
+                f, "needs {} untapped {} gems, you have {}",
+                cost, color, untapped,
+            ),
+            Reason::Untrained => write!(f, "creature is Untrained, it was summoned this turn"),
+            Reason::Tapped => write!(f, "creature is tapped"),
+            Reason::AlreadyBlocking => write!(f, "creature is already blocking"),
+            Reason::AllBlocked => write!(f, "every attacker is already blocked"),
+            Reason::NotSupported => write!(f, "the engine doesn't support it yet"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    pub subject: Subject,
+    /// Why this can't be done, None if it can
+    pub reason: Option<Reason>,
+}
+
+/// Go over the player's hand and creatures, then the other actions
+///
+/// Creatures get attack hints on the player's turn, and block hints on the
+/// enemy's.
+pub fn hints(view: &PlayerView) -> Vec<Hint> {
+    let priority = view.priority == view.player;
+    let our_turn = view.current_player == view.player;
+    // The first reason that applies
+    let check = |reasons: &[(bool, Reason)]| {
+        reasons.iter().find(|(applies, _)| *applies).map(|(_, reason)| reason.clone())
+    };
+    let mut hints = Vec::new();
+
+    let main = our_turn && view.phase == Phase::Main;
+    for (card, c) in view.hand.iter().enumerate() {
+        let (subject, reason) = match view.rules.cost(c.face) {
+            None => {
+                let limit = view.rules.gems_per_turn;
+                (Subject::PlayGem { card }, check(&[
+                    (!priority, Reason::NotYourPriority),
+                    (view.gems_played >= limit, Reason::GemLimit { limit }),
+                    (!main, Reason::WrongPhase { phase: Phase::Main }),
+                ]))
+            }
+            Some(cost) => {
+                let color = c.color();
+                let untapped = view.gems.iter()
+                    .filter(|g| !g.tapped && g.card.color() == color)
+                    .count() as u32;
+                (Subject::Summon { card }, check(&[
+                    (!priority, Reason::NotYourPriority),
+                    (untapped < cost, Reason::NotEnoughGems { cost, color, untapped }),
+                    (!main, Reason::WrongPhase { phase: Phase::Main }),
+                ]))
+            }
+        };
+        hints.push(Hint { subject, reason });
+    }
+
+    for (creature, c) in view.creatures.iter().enumerate() {
+        let hint = if our_turn {
+            Hint {
+                subject: Subject::Attack { creature },
+                reason: check(&[
+                    (!priority, Reason::NotYourPriority),
+                    (c.status == CreatureStatus::Untrained, Reason::Untrained),
+                    (c.status == CreatureStatus::Tapped, Reason::Tapped),
+                    (view.phase != Phase::Attack, Reason::WrongPhase { phase: Phase::Attack }),
+                ]),
+            }
+        } else {
+            let blocking = view.blockers.iter().any(|&(b, _)| b == creature);
+            let unblocked = view.attackers.iter()
+                .any(|&a| !view.blockers.iter().any(|&(_, b)| b == a));
+            Hint {
+                subject: Subject::Block { creature },
+                reason: check(&[
+                    (!priority, Reason::NotYourPriority),
+                    (c.status == CreatureStatus::Tapped, Reason::Tapped),
+                    (view.phase != Phase::Defend, Reason::WrongPhase { phase: Phase::Defend }),
+                    (blocking, Reason::AlreadyBlocking),
+                    (!unblocked, Reason::AllBlocked),
+                ]),
+            }
+        };
+        hints.push(hint);
+    }
+
+    for subject in [Subject::Sacrifice, Subject::Straight, Subject::Stack] {
+        hints.push(Hint { subject, reason: Some(Reason::NotSupported) });
+    }
+    hints.push(Hint {
+        subject: Subject::Pass,
+        reason: check(&[(!priority, Reason::NotYourPriority)]),
+    });
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::game::{Action, Color, CreatureStatus, Game, Phase, Player, deal};
+    use crate::scenario::Scenario;
+
+    use super::{Hint, Reason, Subject, hints};
+
+    #[test]
+    fn test_reasons() {
+        let game = Scenario::new()
+            .hand(0, "2♥ Q♥ J♠")
+            .gems(0, "3♥ 4♥ 2♠ 3♠")
+            .tapped_gems(0, "5♥")
+            .gems_played(0, 1)
+            .creature(0, "J♦", CreatureStatus::Untrained)
+            .creature(0, "K♣", CreatureStatus::Ready)
+            .build();
+        assert_eq!(hints(&game.view_for(0)), [
+            Hint {
+                subject: Subject::PlayGem { card: 0 },
+                reason: Some(Reason::GemLimit { limit: 1 }),
+            },
+            Hint {
+                subject: Subject::Summon { card: 1 },
+                reason: Some(Reason::NotEnoughGems { cost: 4, color: Color::Red, untapped: 2 }),
+            },
+            Hint { subject: Subject::Summon { card: 2 }, reason: None },
+            Hint { subject: Subject::Attack { creature: 0 }, reason: Some(Reason::Untrained) },
+            Hint {
+                subject: Subject::Attack { creature: 1 },
+                reason: Some(Reason::WrongPhase { phase: Phase::Attack }),
+            },
+            Hint { subject: Subject::Sacrifice, reason: Some(Reason::NotSupported) },
+            Hint { subject: Subject::Straight, reason: Some(Reason::NotSupported) },
+            Hint { subject: Subject::Stack, reason: Some(Reason::NotSupported) },
+            Hint { subject: Subject::Pass, reason: None },
+        ]);
+        assert_eq!(
+            Reason::NotEnoughGems { cost: 4, color: Color::Red, untapped: 2 }.to_string(),
+            "needs 4 untapped red gems, you have 2",
+        );
+
+        // The enemy blocks
+        let game = Scenario::new()
+            .turn(1, Phase::Defend)
+            .creature(1, "Q♠", CreatureStatus::Ready).attacking()
+            .creature(0, "J♥", CreatureStatus::Ready).blocking("Q♠")
+            .creature(0, "J♦", CreatureStatus::Tapped)
+            .creature(0, "J♣", CreatureStatus::Ready)
+            .build();
+        let reasons: Vec<Option<Reason>> = hints(&game.view_for(0)).into_iter()
+            .map(|h| h.reason)
+            .collect();
+        assert_eq!(reasons[..], [
+            Some(Reason::AlreadyBlocking),
+            Some(Reason::Tapped),
+            Some(Reason::AllBlocked),
+            Some(Reason::NotSupported),
+            Some(Reason::NotSupported),
+            Some(Reason::NotSupported),
+            None,
+        ]);
+        let reasons: Vec<Option<Reason>> = hints(&game.view_for(1)).into_iter()
+            .map(|h| h.reason)
+            .collect();
+        assert_eq!(reasons[0], Some(Reason::NotYourPriority));
+        assert_eq!(reasons.last(), Some(&Some(Reason::NotYourPriority)));
+    }
+
+    /// The hints agree with `Game::legal_actions()`
+    #[test]
+    fn test_legal_actions() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..20 {
+            let (library0, hand0) = deal(0, &mut rng);
+            let (library1, hand1) = deal(1, &mut rng);
+            let mut game = Game::new([Player::new(library0, hand0), Player::new(library1, hand1)]);
+            game.start();
+            for _ in 0..1000 {
+                if game.outcome().is_some() {
+                    break;
+                }
+                for player in 0..2 {
+                    let legal = game.legal_actions(player);
+                    for hint in hints(&game.view_for(player)) {
+                        let possible = legal.iter().any(|action| match (hint.subject, action) {
+                            (Subject::PlayGem { card }, Action::PlayGem { card: c }) => card == *c,
+                            (Subject::Summon { card }, Action::PlayCreature { card: c, .. }) => card == *c,
+                            (Subject::Attack { creature }, Action::Attack { creature: c }) => creature == *c,
+                            (Subject::Block { creature }, Action::Block { creature: c, .. }) => creature == *c,
+                            (Subject::Pass, Action::Pass) => true,
+                            _ => false,
+                        });
+                        assert_eq!(hint.reason.is_none(), possible, "{:?} {:?}", hint, legal);
+                    }
+                }
+                let player = game.priority();
+                let actions = game.legal_actions(player);
+                let action = actions[rng.gen_range(0..actions.len())].clone();
+                game.apply(player, action).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Property-based tests, playing random games and checking the invariants of
+//! the rules engine after every action
+//!
+//! A game is a seed, used to shuffle the decks, and a list of choices, each
+//! one picking among the legal actions. When an invariant breaks, proptest
+//! shrinks the choices to the shortest game that still breaks it.
+
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::deck::DeckList;
+use crate::game::{Card, CreatureStatus, Face, Game, PickCards, Suit};
+use crate::rules::{Decking, RuleSet, Strengths};
+
+fn key(card: &Card) -> (u32, Suit, Face) {
+    (card.player, card.suit, card.face)
+}
+
+/// Every card in the game, wherever it is
+fn all_cards(game: &Game) -> Vec<(u32, Suit, Face)> {
+    let mut cards = Vec::new();
+    for player in &game.players {
+        cards.extend(player.library.iter().map(key));
+        cards.extend(player.hand.iter().map(key));
+        cards.extend(player.graveyard.iter().map(key));
+        cards.extend(player.gems.iter().map(|g| key(&g.card)));
+        for creature in &player.creatures {
+            cards.extend(creature.cards.iter().map(key));
+            cards.extend(creature.equipment.iter().flatten().map(key));
+        }
+    }
+    cards.sort_unstable();
+    cards
+}
+
+/// The order `add_card` and `add_gem` keep
+fn is_sorted<'a, I: IntoIterator<Item=&'a Card>>(cards: I) -> bool {
+    let keys: Vec<_> = cards.into_iter().map(|c| (c.color(), c.face, c.suit)).collect();
+    keys.windows(2).all(|w| w[0] <= w[1])
+}
+
+fn check(game: &Game, cards: &[(u32, Suit, Face)]) -> Result<(), TestCaseError> {
+    // The 80 cards are all still there, only moved around
+    prop_assert_eq!(all_cards(game), cards);
+
+    for (p, player) in game.players.iter().enumerate() {
+        prop_assert!(is_sorted(&player.hand), "hand of player {} is not sorted", p + 1);
+        prop_assert!(
+            is_sorted(player.gems.iter().map(|g| &g.card)),
+            "gems of player {} are not sorted", p + 1,
+        );
+        prop_assert!(player.gems_played <= game.rules.gems_per_turn);
+        if player.life <= 0 {
+            prop_assert!(game.outcome().is_some());
+        }
+    }
+
+    // The current player's gems are only tapped to pay for the creatures
+    // they summoned this turn, which are the untrained ones
+    let us = &game.players[game.current_player as usize];
+    let tapped = us.gems.iter().filter(|g| g.tapped).count() as u32;
+    let paid: u32 = us.creatures.iter()
+        .filter(|c| c.status == CreatureStatus::Untrained)
+        .map(|c| game.rules.cost(c.cards[0].face).unwrap())
+        .sum();
+    prop_assert_eq!(tapped, paid);
+
+    for &attacker in &game.attackers {
+        prop_assert!(attacker < us.creatures.len());
+    }
+    let enemy = &game.players[1 - game.current_player as usize];
+    for &(blocker, attacker) in &game.blockers {
+        prop_assert!(blocker < enemy.creatures.len());
+        prop_assert!(game.attackers.contains(&attacker));
+    }
+    Ok(())
+}
+
+fn rules() -> impl Strategy<Value = RuleSet> {
+    let strengths = prop::option::of((1..=5i32, 1..=5i32, 1..=5i32, 1..=5i32)
+        .prop_map(|(jack, queen, king, ace)| Strengths { jack, queen, king, ace }));
+    (1..=3u32, any::<bool>(), any::<bool>(), strengths).prop_map(
+        |(gems_per_turn, lose, undo, strengths)| RuleSet {
+            gems_per_turn,
+            decking: if lose { Decking::Lose } else { Decking::Continue },
+            undo,
+            strengths,
+            ..RuleSet::default()
+        },
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_invariants(
+        seed: u64,
+        rules in rules(),
+        choices in prop::collection::vec(any::<prop::sample::Index>(), 0..300),
+    ) {
+        let decks = [DeckList::standard(), DeckList::standard()];
+        let mut picks = PickCards::new(&decks, rules, &mut StdRng::seed_from_u64(seed));
+        picks.accept(0).unwrap();
+        picks.accept(1).unwrap();
+        let mut game = picks.start().unwrap();
+        game.start();
+        let cards = all_cards(&game);
+        prop_assert_eq!(cards.len(), 80);
+        check(&game, &cards)?;
+
+        for choice in choices {
+            if game.outcome().is_some() {
+                break;
+            }
+            let player = game.priority();
+            let actions = game.legal_actions(player);
+            let action = choice.get(&actions).clone();
+
+            // Legal actions are always accepted
+            let result = game.apply(player, action.clone());
+            prop_assert!(result.is_ok(), "{:?} refused: {:?}", action, result);
+            check(&game, &cards)?;
+        }
+    }
+}
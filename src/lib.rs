@@ -0,0 +1,25 @@
+//! The rules of Superjack
+//!
+//! The engine doesn't do any input or output, so it can be used by the
+//! terminal interface, the servers, or compiled to WebAssembly (with the
+//! `wasm` feature).
+
+#[cfg(test)]
+mod conformance;
+pub mod bot;
+pub mod deck;
+pub mod game;
+pub mod hints;
+#[cfg(test)]
+mod invariants;
+pub mod matches;
+pub mod pay;
+pub mod puzzle;
+pub mod ratings;
+pub mod replay;
+pub mod rules;
+pub mod scenario;
+pub mod tournament;
+pub mod tutorial;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,26 +1,61 @@
-use rand::{Rng, thread_rng};
-use std::io::Write;
+mod cli;
+mod command;
+mod net;
+mod tui;
+#[cfg(feature = "web")]
+mod web;
+
+use superjack::bot::new_bot;
+use superjack::deck::{DeckError, DeckList, DeckRules};
+use superjack::game::Outcome;
+use superjack::matches::{FirstPlayer, Match};
+use superjack::puzzle::Puzzle;
+use superjack::ratings::Registry;
+use superjack::tournament;
+use superjack::replay::Replay;
+use superjack::rules::RuleSet;
 
 #[derive(Debug)]
 enum Error {
     Exit,
     Io(std::io::Error),
+    Protocol(String),
+    Deck(String, DeckError),
+    Rules(String, String),
+    Replay(String, String),
+    Puzzle(String, String),
+    Ratings(String, String),
+    Tournament(String, String),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            &Error::Exit => write!(f, "User requested exit"),
-            &Error::Io(ref e) => write!(f, "{}", e),
+        match *self {
+            Error::Exit => write!(f, "User requested exit"),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Protocol(ref e) => write!(f, "Protocol error: {}", e),
+            Error::Deck(ref path, ref e) => write!(f, "{}: {}", path, e),
+            Error::Rules(ref path, ref e) => write!(f, "{}: {}", path, e),
+            Error::Replay(ref path, ref e) => write!(f, "{}: {}", path, e),
+            Error::Puzzle(ref path, ref e) => write!(f, "{}: {}", path, e),
+            Error::Ratings(ref path, ref e) => write!(f, "{}: {}", path, e),
+            Error::Tournament(ref path, ref e) => write!(f, "{}: {}", path, e),
         }
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            &Error::Exit => None,
-            &Error::Io(ref e) => Some(e),
+        match *self {
+            Error::Exit => None,
+            Error::Io(ref e) => Some(e),
+            Error::Protocol(_) => None,
+            Error::Deck(_, ref e) => Some(e),
+            Error::Rules(_, _) => None,
+            Error::Replay(_, _) => None,
+            Error::Puzzle(_, _) => None,
+            Error::Ratings(_, _) => None,
+            Error::Tournament(_, _) => None,
         }
     }
 }
@@ -31,790 +66,451 @@ impl From<std::io::Error> for Error {
     }
 }
 
-fn main() {
-    match Game::play() {
-        Ok(()) => {}
-        Err(Error::Exit) => {}
-        Err(e) => {
-            eprintln!("Error: {}", e);
-        }
-    }
-}
-
-fn read_yes_no(prompt: &str, default: Option<bool>) -> Result<bool, Error> {
-    let options = match default {
-        None => "y/n",
-        Some(true) => "Y/n",
-        Some(false) => "y/N",
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Protocol(e.to_string())
+    }
+}
+
+/// Where ratings are kept, in the current directory
+const DEFAULT_RATINGS: &str = "superjack-ratings.json";
+
+fn usage() {
+    eprintln!("Usage:");
+    eprintln!("    superjack [options]          Play on this terminal");
+    eprintln!("    superjack tui [options]      Play on this terminal, full-screen");
+    eprintln!("    superjack server [address]   Host games over the network");
+    eprintln!("    superjack connect [address]  Join a game hosted on a server");
+    eprintln!("    superjack spectate <game> [address]");
+    eprintln!("                                 Watch a game hosted on a server");
+    if cfg!(feature = "web") {
+        eprintln!("    superjack web [address]      Serve the HTTP and WebSocket API");
+    }
+    eprintln!("    superjack trace <replay>     Print a recorded game as a PlantUML");
+    eprintln!("                                 sequence diagram");
+    eprintln!("    superjack puzzle <file>      Find how to win in one turn");
+    eprintln!("    superjack tutorial           Learn the rules step by step");
+    eprintln!("    superjack stats [name]       Show players' records and games");
+    eprintln!("    superjack leaderboard        Rank players by rating");
+    eprintln!("    superjack tournament <file>  Run a tournament between bots and");
+    eprintln!("                                 humans");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("    --hotseat       Hide the screen between players so they don't");
+    eprintln!("                    see each other's cards");
+    eprintln!("    --deck1 <file>  Deck list for player 1 (also for server)");
+    eprintln!("    --deck2 <file>  Deck list for player 2 (also for server)");
+    eprintln!("    --rules <file>  Rule variants, as TOML or JSON (also for server)");
+    eprintln!("    --record <file> Save the game, to replay or trace it");
+    eprintln!("    --delay <turns> Spectate with full information, that many turns");
+    eprintln!("                    late ({} to {}), with --caster", net::MIN_DELAY, net::MAX_DELAY);
+    eprintln!("    --caster <token>");
+    eprintln!("                    Token that spectators need to see everything, for the");
+    eprintln!("                    server and when spectating");
+    eprintln!("    --name1 <name>  Name of player 1, to rate the game");
+    eprintln!("    --name2 <name>  Name of player 2, to rate the game");
+    eprintln!("    --name <name>   Your name, to rate a game on a server");
+    eprintln!("    --ratings <file>");
+    eprintln!("                    Ratings and match history (default {})", DEFAULT_RATINGS);
+    eprintln!("    --best-of <n>   Play a local match of up to n games, taking turns");
+    eprintln!("                    going first, each game is recorded in its own file");
+    eprintln!("    --loser-chooses With --best-of, the loser of a game picks who goes");
+    eprintln!("                    first in the next one");
+}
+
+/// The file recording one game of a match, `game.json` becoming `game-2.json`
+fn game_record(path: &str, game: usize) -> String {
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, game, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, game),
     };
-    let stdin = std::io::stdin();
-    let mut stdout = std::io::stdout();
-    let mut buffer = String::new();
-    loop {
-        write!(stdout, "{} [{}] ", prompt, options)?;
-        stdout.flush()?;
-        match stdin.read_line(&mut buffer) {
-            Ok(0) => return Err(Error::Exit),
-            Err(e) => return Err(Error::Io(e)),
-            Ok(_) => {
-                let response = buffer.trim().to_lowercase();
-                if response == "yes" || response == "y" {
-                    return Ok(true);
-                } else if response == "no" || response == "n" {
-                    return Ok(false);
-                } else if response == "" && default.is_some() {
-                    return Ok(default.unwrap());
-                } else {
-                    println!("I didn't understand {:?}", buffer.trim());
-                }
-            }
-        }
-        buffer.clear();
-    }
+    path.with_file_name(name).to_string_lossy().into_owned()
 }
 
-fn read_number(prompt: &str, max: i32, cancellable: bool, empty: bool) -> Result<i32, Error> {
-    let mut options = format!("1-{}", max);
-    if cancellable {
-        options.push_str(" or 0");
-    }
-    if empty {
-        options.push_str(" or enter");
-    }
-    let stdin = std::io::stdin();
-    let mut stdout = std::io::stdout();
-    let mut buffer = String::new();
-    loop {
-        write!(stdout, "{} [{}] ", prompt, options)?;
-        stdout.flush()?;
-        match stdin.read_line(&mut buffer) {
-            Ok(0) => return Err(Error::Exit),
-            Err(e) => return Err(Error::Io(e)),
-            Ok(_) => {
-                let response = buffer.trim();
-                if response == "" && empty {
-                    return Ok(-1);
-                }
-                match response.parse::<i32>() {
-                    Ok(0) if cancellable => return Ok(0),
-                    Ok(i) if 1 <= i && i <= max => {
-                        return Ok(i);
-                    }
-                    _ => {
-                        println!("Invalid value");
-                    }
-                }
-            }
-        }
-        buffer.clear();
-    }
+/// Read and validate a deck list file
+fn load_deck(path: &str, rules: &DeckRules) -> Result<DeckList, Error> {
+    let text = std::fs::read_to_string(path)?;
+    let deck = DeckList::parse(&text, rules).map_err(|e| Error::Deck(path.to_owned(), e))?;
+    deck.validate(rules).map_err(|e| Error::Deck(path.to_owned(), e))?;
+    Ok(deck)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Face {
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Jack,
-    Queen,
-    King,
-    Ace,
+/// Read a rule set, from JSON if the file has a `.json` extension or TOML
+/// otherwise
+fn load_rules(path: &str) -> Result<RuleSet, Error> {
+    let text = std::fs::read_to_string(path)?;
+    let rules = if path.ends_with(".json") {
+        RuleSet::from_json(&text).map_err(|e| e.to_string())
+    } else {
+        RuleSet::from_toml(&text).map_err(|e| e.to_string())
+    };
+    rules.map_err(|e| Error::Rules(path.to_owned(), e))
 }
 
-impl Face {
-    fn all() -> &'static [Face] {
-        &[
-            Face::Two,
-            Face::Three,
-            Face::Four,
-            Face::Five,
-            Face::Six,
-            Face::Seven,
-            Face::Jack,
-            Face::Queen,
-            Face::King,
-            Face::Ace,
-        ]
-    }
-
-    fn next(&self) -> Option<Face> {
-        match *self {
-            Face::Two => Some(Face::Three),
-            Face::Three => Some(Face::Four),
-            Face::Four => Some(Face::Five),
-            Face::Five => Some(Face::Six),
-            Face::Six => Some(Face::Seven),
-            Face::Seven => None,
-            _ => None,
-        }
-    }
+/// Print a recorded game as a PlantUML sequence diagram
+fn trace(path: &str) -> Result<(), Error> {
+    let text = std::fs::read_to_string(path)?;
+    let replay = Replay::from_json(&text).map_err(|e| Error::Replay(path.to_owned(), e.to_string()))?;
+    let trace = replay.trace().map_err(|e| Error::Replay(path.to_owned(), e.to_string()))?;
+    print!("{}", trace);
+    Ok(())
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Suit {
-    Spades,
-    Hearts,
-    Diamonds,
-    Clubs,
+fn load_puzzle(path: &str) -> Result<Puzzle, Error> {
+    let text = std::fs::read_to_string(path)?;
+    Puzzle::from_toml(&text).map_err(|e| Error::Puzzle(path.to_owned(), e.to_string()))
 }
 
-impl Suit {
-    fn all() -> &'static [Suit] {
-        &[Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs]
+/// Read the ratings, which start empty if the file doesn't exist yet
+fn load_ratings(path: &str) -> Result<Registry, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Registry::from_json(&text).map_err(|e| Error::Ratings(path.to_owned(), e.to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Registry::default()),
+        Err(e) => Err(e.into()),
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Color {
-    Red,
-    Black,
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
 }
 
-impl Color {
-    fn all() -> &'static [Color] {
-        &[Color::Red, Color::Black]
+/// Record a finished game between named players
+fn rate(path: &str, names: &[String; 2], outcome: Outcome) -> Result<(), Error> {
+    let mut registry = load_ratings(path)?;
+    registry.add_game([&names[0], &names[1]], outcome, now());
+    std::fs::write(path, registry.to_json())?;
+    for name in names {
+        println!("{} is now rated {:.0}", name, registry.record(name).rating);
     }
+    Ok(())
 }
 
-impl std::fmt::Display for Color {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match *self {
-            Color::Red => write!(f, "red"),
-            Color::Black => write!(f, "black"),
-        }
-    }
-}
-
-impl Suit {
-    fn color(&self) -> Color {
-        match *self {
-            Suit::Spades | Suit::Clubs => Color::Black,
-            Suit::Hearts | Suit::Diamonds => Color::Red,
+/// Play games on this terminal until someone wins the match
+///
+/// Named players have each game rated. If they brought their own decks, they
+/// can switch to other deck lists between games.
+fn play_match(
+    console: &mut cli::Console,
+    mut m: Match,
+    names: Option<&[String; 2]>,
+    ratings_file: &str,
+    change_decks: bool,
+    mut play: impl FnMut(&mut cli::Console, usize, &[DeckList; 2]) -> Result<Outcome, Error>,
+) -> Result<(), Error> {
+    let labels = match names {
+        Some(names) => names.clone(),
+        None => ["Player 1".to_owned(), "Player 2".to_owned()],
+    };
+    while m.outcome().is_none() {
+        if let Some(chooser) = m.chooser() {
+            let first = cli::read_yes_no(
+                console,
+                &format!("{}, you lost, do you want to go first?", labels[chooser as usize]),
+                Some(true),
+            )?;
+            m.choose_first(chooser, if first { chooser } else { 1 - chooser }).unwrap();
         }
-    }
-}
-
-#[derive(Debug)]
-struct Card {
-    player: u32,
-    suit: Suit,
-    face: Face,
-}
-
-impl Card {
-    fn color(&self) -> Color {
-        self.suit.color()
-    }
-}
-
-impl std::fmt::Display for Card {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let face = match self.face {
-            Face::Two => "2",
-            Face::Three => "3",
-            Face::Four => "4",
-            Face::Five => "5",
-            Face::Six => "6",
-            Face::Seven => "7",
-            Face::Jack => "J",
-            Face::Queen => "Q",
-            Face::King => "K",
-            Face::Ace => "A",
-        };
-        let suit = match self.suit {
-            Suit::Spades => "\u{2660}",
-            Suit::Hearts => "\u{2665}",
-            Suit::Diamonds => "\u{2666}",
-            Suit::Clubs => "\u{2663}",
-        };
-        write!(f, "{}{}", face, suit)
-    }
-}
-
-#[derive(Debug)]
-struct Gem {
-    card: Card,
-    tapped: bool,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum CreatureStatus {
-    Ready,
-    Tapped,
-    Untrained,
-}
-
-#[derive(Debug)]
-struct Creature {
-    cards: Vec<Card>,
-    royal_charge: bool,
-    status: CreatureStatus,
-    equipment: Vec<[Card; 2]>,
-}
-
-#[derive(Debug)]
-struct Player {
-    library: Vec<Card>,
-    graveyard: Vec<Card>,
-    hand: Vec<Card>,
-    life: i32,
-    gems: Vec<Gem>,
-    creatures: Vec<Creature>,
-    has_played_gem: bool,
-}
-
-impl Player {
-    /// Add card, keeping the hand sorted
-    fn add_card(&mut self, card: Card) {
-        let card_key = |c: &Card| (
-            c.color(),
-            c.face,
-            c.suit,
-        );
-        let pos = self.hand.partition_point(|c| card_key(c) < card_key(&card));
-        self.hand.insert(pos, card);
-    }
-
-    /// Add gem, keeping them sorted
-    fn add_gem(&mut self, card: Card) {
-        let gem_key = |c: &Card| (
-            c.color(),
-            c.face,
-            c.suit,
-        );
-        let pos = self.gems.partition_point(|c| gem_key(&c.card) < gem_key(&card));
-        self.gems.insert(pos, Gem { card, tapped: false });
-    }
-
-    /// Add creature, keeping them sorted
-    fn add_creature(&mut self, creature: Creature) {
-        let creature_key = |c: &Creature| (
-            c.cards[0].face,
-            c.cards[0].color(),
-            c.cards[0].suit,
+        let seats = m.next_game().unwrap();
+        println!(
+            "Game {}: {} goes first as Player 1, {} is Player 2",
+            m.games.len() + 1, labels[seats[0] as usize], labels[seats[1] as usize],
         );
-        let pos = self.creatures.partition_point(|c| creature_key(c) < creature_key(&creature));
-        self.creatures.insert(pos, creature);
-    }
-
-    fn reset(&mut self) {
-        for gem in &mut self.gems {
-            gem.tapped = false;
+        let outcome = play(console, m.games.len() + 1, &m.seated_decks().unwrap())?;
+        if let Some(names) = names {
+            rate(ratings_file, &seats.map(|p| names[p as usize].clone()), outcome)?;
         }
-        for creature in &mut self.creatures {
-            creature.status = CreatureStatus::Ready;
+        m.finish_game(outcome).unwrap();
+        println!("Score: {} {}, {} {}, {} draws", labels[0], m.wins[0], labels[1], m.wins[1], m.draws);
+
+        if change_decks && m.outcome().is_none() {
+            for participant in 0..2 {
+                loop {
+                    let path = cli::read_line(console, &format!(
+                        "{}, deck list for the next game (enter to keep yours):",
+                        labels[participant as usize],
+                    ))?;
+                    if path.is_empty() {
+                        break;
+                    }
+                    match load_deck(&path, &m.rules.deck) {
+                        Ok(deck) => {
+                            m.change_deck(participant, deck).unwrap();
+                            break;
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+            }
         }
-        self.has_played_gem = false;
     }
+    match m.outcome() {
+        Some(Outcome::Win(winner)) => println!(
+            "{} wins the match {}-{}",
+            labels[winner as usize], m.wins[winner as usize], m.wins[1 - winner as usize],
+        ),
+        _ => println!("The match is a draw"),
+    }
+    Ok(())
 }
 
-fn random_deck<R: Rng>(player: u32, rng: &mut R) -> Vec<Card> {
-    let mut deck = Vec::with_capacity(40);
-    for &suit in Suit::all() {
-        for &face in Face::all() {
-            deck.push(Card {
-                player,
-                suit,
-                face,
-            });
+fn stats(path: &str, name: Option<&str>) -> Result<(), Error> {
+    let registry = load_ratings(path)?;
+    let Some(name) = name else {
+        for (name, record) in &registry.players {
+            println!(
+                "{}: rated {:.0}, {} wins, {} losses, {} draws",
+                name, record.rating, record.wins, record.losses, record.draws,
+            );
+        }
+        return Ok(());
+    };
+    let record = registry.record(name);
+    println!("{}: rated {:.0}", name, record.rating);
+    println!("{} games, {} wins, {} losses, {} draws", record.games(), record.wins, record.losses, record.draws);
+    for game in registry.history(name) {
+        let us = if game.players[0] == name { 0 } else { 1 };
+        let result = match game.outcome {
+            Outcome::Draw => "draw",
+            Outcome::Win(winner) if winner == us => "win",
+            Outcome::Win(_) => "loss",
+        };
+        println!("    {} against {}, {:+.0}", result, game.players[1 - us as usize], game.changes[us as usize]);
+    }
+    Ok(())
+}
+
+fn leaderboard(path: &str) -> Result<(), Error> {
+    let registry = load_ratings(path)?;
+    for (rank, (name, record)) in registry.leaderboard().into_iter().enumerate() {
+        println!("{:>4}. {:<20} {:>5.0} ({} games)", rank + 1, name, record.rating, record.games());
+    }
+    Ok(())
+}
+
+/// Run a tournament, and record every game with the players' ratings
+///
+/// Games with a human are played at the terminal, hiding the screen between
+/// two humans in hotseat mode.
+fn run_tournament(path: &str, ratings_file: &str, hotseat: bool) -> Result<(), Error> {
+    let text = std::fs::read_to_string(path)?;
+    let config = tournament::Config::from_toml(&text).map_err(|e| Error::Tournament(path.to_owned(), e))?;
+    let mut console = cli::Console::stdio();
+    let results = tournament::run(&config, |decks, rules, seats| {
+        println!("\n{} is Player 1, {} is Player 2", seats[0].name, seats[1].name);
+        cli::play_with(&mut console, hotseat, decks, rules, None, seats.map(|p| p.strategy.map(new_bot)))
+    })?;
+    let name = |player: usize| config.players[player].name.as_str();
+
+    let mut registry = load_ratings(ratings_file)?;
+    let time = now();
+    for pairing in &results.pairings {
+        for &outcome in &pairing.outcomes {
+            registry.add_game(pairing.players.map(name), outcome, time);
         }
     }
-    {
-        let slice: &mut [Card] = &mut deck;
-        rand::seq::SliceRandom::shuffle(slice, rng);
-    }
-    deck
-}
-
-fn sort_hand(hand: &mut [Card]) {
-    hand.sort_by_key(|card| (
-        card.color(),
-        card.face,
-        card.suit,
-    ));
-}
+    std::fs::write(ratings_file, registry.to_json())?;
 
-fn show_hand(hand: &[Card], player: u32) {
-    if hand.is_empty() {
-        println!("    (no cards)");
-    }
-    for (i, card) in hand.into_iter().enumerate() {
+    println!("Standings:");
+    for (rank, standing) in results.standings(config.players.len()).iter().enumerate() {
         println!(
-            "{:>6} - {}{}",
-            i + 1,
-            card,
-            // Show a '*' if card belongs to the other player (mind controlled)
-            if card.player != player { "*" } else { "" },
+            "{:>4}. {:<20} {:>3} points, matches {}-{}-{}, games {}-{}-{}",
+            rank + 1, name(standing.player), standing.points,
+            standing.matches[0], standing.matches[1], standing.matches[2],
+            standing.games[0], standing.games[1], standing.games[2],
         );
     }
-}
-
-fn show_gems(gems: &[Gem]) {
-    if gems.is_empty() {
-        println!("    (no gems)");
-    }
-    let mut prev_color = None;
-    for (i, gem) in gems.iter().enumerate() {
-        let color = gem.card.color();
-        if prev_color != Some(color) {
-            prev_color = Some(color);
-            println!("  {}:", color);
-        } else {
-            prev_color = Some(color);
-        }
+    println!();
+    println!("Pairings:");
+    for pairing in &results.pairings {
+        let [a, b] = pairing.players;
         println!(
-            "{:>6} - {}{}",
-            i + 1,
-            gem.card,
-            if gem.tapped { " TAPPED" } else { "" },
+            "    Round {}: {} {}-{}{} {}, first player won {} of {} games, {:.1} turns per game",
+            pairing.round, name(a), pairing.wins[0], pairing.wins[1],
+            if pairing.draws > 0 { format!("-{}", pairing.draws) } else { String::new() },
+            name(b), pairing.first_player_wins, pairing.games(),
+            pairing.turns as f64 / pairing.games() as f64,
         );
     }
-}
-
-fn show_creatures(creatures: &[Creature], player: u32) {
-    if creatures.is_empty() {
-        println!("    (no creatures)");
-    }
-    for (i, creature) in creatures.into_iter().enumerate() {
-        print!("{:>6} - ", i + 1);
-        // Show card(s)
-        for (i, card) in creature.cards.iter().enumerate() {
-            if i > 0 {
-                print!(", ");
-            }
-            print!(
-                "{}{}",
-                card,
-                // Show a '*' if card belongs to the other player (mind
-                // controlled)
-                if card.player != player { "*" } else { "" },
-            );
-        }
-        println!();
-        // Show status
-        match creature.status {
-            CreatureStatus::Ready => {
-                if creature.royal_charge {
-                    println!("       Ready, Royal Charge");
-                } else {
-                    println!("       Ready");
-                }
-            }
-            CreatureStatus::Tapped => println!("       Tapped"),
-            CreatureStatus::Untrained => println!("       Untrained"),
-        }
+    for &(round, player) in &results.byes {
+        println!("    Round {}: {} has a bye", round, name(player));
     }
+    println!();
+    println!("Games recorded in {}", ratings_file);
+    Ok(())
 }
 
-enum PickedGems {
-    No,
-    Yes,
-    Straight,
-}
-
-fn is_straight<I: IntoIterator<Item=Face>>(card_faces: I) -> bool {
-    // This relies on the fact that the hand is sorted by face
-    let mut is_straight = true;
-    let mut prev_face: Option<Face> = None;
-    for face in card_faces {
-        if let Some(prev_face) = prev_face {
-            if Some(face) != prev_face.next() {
-                is_straight = false;
-                break;
-            }
+/// Exit with an error message if loading a file failed
+fn or_exit<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
-        prev_face = Some(face);
     }
-    is_straight
-}
-
-struct Game {
-    current_player: u32,
-    players: [Player; 2],
 }
 
-impl Game {
-    fn play() -> Result<(), Error> {
-        let mut rng = thread_rng();
-
-        println!("New game!");
-
-        // Pick players' decks and starting hands
-        let mut decks: Vec<(Vec<Card>, Vec<Card>)> = Vec::new();
-        for player in [0, 1] {
-            for mulliganed in [false, true] {
-                // Make a random deck
-                let mut deck = random_deck(player, &mut rng);
-
-                // Draw 5 cards
-                let mut hand: Vec<Card> = deck.drain(deck.len() - 5..).collect();
-                sort_hand(&mut hand);
-                println!("\nPlayer {} draws cards:", player + 1);
-                show_hand(&hand, player);
-
-                // Mulligan?
-                if !mulliganed {
-                    if read_yes_no("Mulligan?", Some(false))? {
-                        continue;
-                    }
-                }
-
-                decks.push((deck, hand));
-                break;
-            }
-        }
-
-        // Create players
-        let mut players: Vec<Player> = Vec::new();
-        for (library, hand) in decks {
-            players.push(Player {
-                library,
-                graveyard: Vec::new(),
-                hand,
-                life: 20,
-                gems: Vec::new(),
-                creatures: Vec::new(),
-                has_played_gem: false,
-            });
-        }
-
-        let mut game = Game {
-            current_player: 0,
-            players: players.try_into().map_err(|_| "Vec to [Player; 2]").unwrap(),
-        };
-        // Loop until someone wins
-        game.main_loop()
-    }
-
-    fn us(&self) -> &Player {
-        &self.players[self.current_player as usize]
-    }
-
-    fn us_mut(&mut self) -> &mut Player {
-        &mut self.players[self.current_player as usize]
-    }
-
-    fn enemy(&self) -> &Player {
-        &self.players[(1 - self.current_player) as usize]
-    }
-
-    fn show_status(&self) {
-        println!();
-        println!("Your life: {}", self.us().life);
-        println!("Enemy life: {}", self.enemy().life);
-        println!("Enemy has {} cards", self.enemy().hand.len());
-        println!("Enemy's gems:");
-        show_gems(&self.enemy().gems);
-        println!("Enemy's creatures:");
-        show_creatures(&self.enemy().creatures, 1 - self.current_player);
-        println!("Your creatures:");
-        show_creatures(&self.us().creatures, self.current_player);
-        println!("Your gems:");
-        show_gems(&self.us().gems);
-        println!("Your cards:");
-        show_hand(&self.us().hand, self.current_player);
-    }
-
-    fn main_loop(&mut self) -> Result<(), Error> {
-        loop {
-            println!("\nPlayer {}'s turn\n", self.current_player + 1);
-
-            // Draw a card
-            match self.us_mut().library.pop() {
-                Some(card) => {
-                    println!("You draw a card: {}", card);
-                    self.us_mut().add_card(card);
-                }
-                None => println!("Can't draw, no cards left"),
-            }
-
-            // Reset everything
-            self.us_mut().reset();
-
-            // Main phase
-            loop {
-                self.show_status();
-
-                let untapped_gems = self.us().gems.iter().filter(|g| !g.tapped).count();
-                let untapped_jacks = self.us().creatures.iter().filter(|c| c.cards[0].face == Face::Jack).count();
-                let can_royal_sacrifice = Color::all().iter()
-                    // For each color
-                    .any(|&col| {
-                        // check that we have one of each
-                        let mut found = 0;
-                        for creature in &self.us().creatures {
-                            for card in &creature.cards {
-                                if card.color() == col {
-                                    found |= match card.face {
-                                        Face::Jack => 0x01,
-                                        Face::Queen => 0x02,
-                                        Face::King => 0x04,
-                                        _ => 0,
-                                    };
-                                }
-                            }
+fn main() {
+    let mut hotseat = false;
+    let mut deck_files = [None, None];
+    let mut rules_file = None;
+    let mut delay = None;
+    let mut caster = None;
+    let mut record = None;
+    let mut names = [None, None];
+    let mut name = None;
+    let mut ratings_file = DEFAULT_RATINGS.to_owned();
+    let mut best_of = None;
+    let mut first_player = FirstPlayer::Alternate;
+    let mut args = Vec::new();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--hotseat" => hotseat = true,
+            "--loser-chooses" => first_player = FirstPlayer::LoserChooses,
+            "--deck1" | "--deck2" | "--rules" | "--delay" | "--record" | "--name1" | "--name2"
+            | "--name" | "--ratings" | "--best-of" | "--caster" => {
+                let Some(value) = iter.next() else {
+                    usage();
+                    std::process::exit(2);
+                };
+                match arg.as_str() {
+                    "--deck1" => deck_files[0] = Some(value),
+                    "--deck2" => deck_files[1] = Some(value),
+                    "--record" => record = Some(value),
+                    "--name1" => names[0] = Some(value),
+                    "--name2" => names[1] = Some(value),
+                    "--name" => name = Some(value),
+                    "--ratings" => ratings_file = value,
+                    "--caster" => caster = Some(value),
+                    "--delay" => match value.parse::<u32>() {
+                        Ok(turns) if (net::MIN_DELAY..=net::MAX_DELAY).contains(&turns) => {
+                            delay = Some(turns)
                         }
-                        found == 0x07
-                    });
-
-                if self.us().hand.len() > 0 && read_yes_no("Play a card?", Some(false))? {
-                    // Play cards from our hand
-                    let card_num = read_number(
-                        "Which card to play?",
-                        self.us().hand.len() as i32,
-                        true,
-                        false,
-                    )?;
-                    if card_num == 0 {
-                        break;
-                    }
-                    let card = self.us_mut().hand.remove(
-                        (card_num - 1) as usize,
-                    );
-                    match self.play_card(card, self.current_player)? {
-                        None => {}
-                        Some(card) => {
-                            // Add card back into hand
-                            self.us_mut().add_card(card);
+                        _ => {
+                            usage();
+                            std::process::exit(2);
                         }
-                    }
-                } else if untapped_gems > 0 && read_yes_no("Play a straight?", Some(false))? {
-                    // Play a straight from our gems
-                    todo!();
-                } else if untapped_gems > 0 && read_yes_no("Sacrifice a gem?", Some(false))? {
-                    // Sacrifice one of our gems
-                    todo!();
-                } else if untapped_jacks >= 2 && read_yes_no("Stack jacks?", Some(false))? {
-                    // Stack untapped jacks from our creatures
-                    todo!();
-                } else if can_royal_sacrifice && read_yes_no("Royal sacrifice?", Some(false))? {
-                    // Royal sacrifice
-                    todo!();
-                } else {
-                    break;
+                    },
+                    "--best-of" => match value.parse::<u32>() {
+                        Ok(games) if games > 0 => best_of = Some(games),
+                        _ => {
+                            usage();
+                            std::process::exit(2);
+                        }
+                    },
+                    _ => rules_file = Some(value),
                 }
             }
-
-            // TODO: Attack, defense
-
-            // TODO: Second main phase
-
-            // Check victory condition
-            if self.players[0].life <= 0 && self.players[1].life <= 0 {
-                println!("It's a draw!");
-                return Ok(());
-            } else if self.players[0].life <= 0 {
-                println!("Player 2 wins");
-                return Ok(());
-            } else if self.players[1].life <= 0 {
-                println!("Player 1 wins");
-                return Ok(());
-            }
-
-            // Next turn
-            self.current_player = 1 - self.current_player;
+            _ => args.push(arg),
         }
     }
-
-    /// Try to play a card, asking relevant questions.
-    ///
-    /// Returns Ok(None) if the card was played, or Ok(Some(card)) if the card
-    /// couldn't be played and should be returned to the player's hand.
-    fn play_card(&mut self, card: Card, player: u32) -> Result<Option<Card>, Error> {
-        match card.face {
-            Face::Two | Face::Three | Face::Four
-            | Face::Five | Face::Six | Face::Seven => {
-                if self.players[player as usize].has_played_gem {
-                    println!("You can only play one gem per turn");
-                    Ok(Some(card))
-                } else {
-                    println!("Adding gem {}", card);
-                    self.players[player as usize].has_played_gem = true;
-                    self.players[player as usize].add_gem(card);
-                    Ok(None)
-                }
-            }
-            Face::Jack | Face::Queen | Face::King => {
-                let cost = match card.face {
-                    Face::Jack => 2,
-                    Face::Queen => 4,
-                    Face::King => 5,
-                    Face::Ace => 3,
-                    _ => unreachable!(),
-                };
-                let royal_charge = match self.pick_gems(cost, player, card.color())? {
-                    PickedGems::No => return Ok(Some(card)),
-                    PickedGems::Yes => false,
-                    PickedGems::Straight => true,
-                };
-                println!("Adding creature {} (untrained)", card);
-                if royal_charge {
-                    println!("Creature has Royal Charge!");
-                }
-                let creature = Creature {
-                    cards: vec![card],
-                    royal_charge,
-                    status: CreatureStatus::Untrained,
-                    equipment: Vec::new(),
-                };
-                self.players[player as usize].add_creature(creature);
-                Ok(None)
-            }
-            Face::Ace => {
-                if read_yes_no("Play as spell? [y/n] ", None)? {
-                    todo!();
-                } else {
-                    let royal_charge = match self.pick_gems(3, player, card.color())? {
-                        PickedGems::No => return Ok(Some(card)),
-                        PickedGems::Yes => false,
-                        PickedGems::Straight => true,
-                    };
-                    println!("Adding creature {} (trained)", card);
-                    if royal_charge {
-                        println!("Creature has Royal Charge!");
-                    }
-                    let creature = Creature {
-                        cards: vec![card],
-                        royal_charge,
-                        status: CreatureStatus::Untrained,
-                        equipment: Vec::new(),
-                    };
-                    self.players[player as usize].add_creature(creature);
-                    Ok(None)
-                }
-            }
+    let rules = match rules_file {
+        Some(path) => or_exit(load_rules(&path)),
+        None => RuleSet::default(),
+    };
+    let mut decks = [DeckList::standard(), DeckList::standard()];
+    for (deck, path) in decks.iter_mut().zip(&deck_files) {
+        if let Some(path) = path {
+            *deck = or_exit(load_deck(path, &rules.deck));
         }
     }
 
-    fn pick_gems(&mut self, cost: u32, player: u32, color: Color) -> Result<PickedGems, Error> {
-        // TODO: Gem sacrifice 4 or 5 to "mine" (+3 energy)
-
-        // Create vector of same size as gems
-        // For gems we can't select: None
-        // For gems we can select Some(true) if selected, Some(false) if unselected
-        let mut choices = Vec::new();
-        let mut num_choices = 0;
-        for gem in &self.players[player as usize].gems {
-            if !gem.tapped && gem.card.color() == color {
-                choices.push(Some(false));
-                num_choices += 1;
-            } else {
-                choices.push(None);
-            }
+    // Games are rated when both players are named
+    let names = match names {
+        [Some(name1), Some(name2)] if name1 == name2 => {
+            eprintln!("Error: both players are named {}", name1);
+            std::process::exit(2);
         }
-        let mut num_selected = 0;
-
-        // Loop until gems are selected and confirmed
-        loop {
-            for (i, (gem, selected)) in
-                self.players[player as usize].gems.iter().zip(&choices)
-                    .filter_map(|(gem, &choice)|
-                        match choice { Some(c) => Some((gem, c)), None => None }
-                    )
-                    .enumerate()
-            {
-                println!(
-                    "{:>6} - {} {}",
-                    i + 1,
-                    if selected { "[x]" } else { "[ ]" },
-                    gem.card,
-                );
-            }
-            match read_number(
-                &format!("Pick gems ({}/{})", num_selected, cost),
-                num_choices,
-                true,
-                num_selected == cost,
-            )? {
-                // Cancelled
-                0 => return Ok(PickedGems::No),
-                // Confirmed
-                -1 => {
-                    // Tap selected gems
-                    for (gem, &selected) in self.players[player as usize].gems.iter_mut().zip(&choices) {
-                        if let Some(true) = selected {
-                            gem.tapped = true;
-                        }
-                    }
-
-                    // Check for straights
-                    // This relies on the fact that the hand is sorted by face
-                    let is_straight = is_straight(
-                        self.players[player as usize].gems.iter().zip(&choices)
-                            .filter_map(|(gem, &selected)|
-                                if let Some(true) = selected {
-                                    Some(gem.card.face)
-                                } else {
-                                    None
-                                }
-                            )
-                    );
+        [Some(name1), Some(name2)] => Some([name1, name2]),
+        [None, None] => None,
+        _ => {
+            usage();
+            std::process::exit(2);
+        }
+    };
+    let rated = |outcome| match names {
+        Some(ref names) => rate(&ratings_file, names, outcome),
+        None => Ok(()),
+    };
 
-                    if is_straight {
-                        return Ok(PickedGems::Straight);
-                    } else {
-                        return Ok(PickedGems::Yes);
-                    }
-                }
-                // Toggle a gem
-                i => {
-                    match choices.iter_mut()
-                        .filter_map(|s| s.as_mut())
-                        .nth((i - 1) as usize)
-                    {
-                        Some(selected) => match *selected {
-                            true => {
-                                *selected = false;
-                                num_selected -= 1;
-                            }
-                            false => {
-                                *selected = true;
-                                num_selected += 1;
-                            }
-                        }
-                        None => panic!(),
-                    }
+    let new_match = |games| Match::new(decks.clone(), rules.clone(), games, first_player);
+    let change_decks = deck_files.iter().any(Option::is_some);
+
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+    // Matches are only played locally
+    let match_options = best_of.is_some() || first_player == FirstPlayer::LoserChooses;
+    if match_options && (best_of.is_none() || !matches!(args[..], [] | ["tui"])) {
+        usage();
+        std::process::exit(2);
+    }
+    let record_file = |game| record.as_deref().map(|path| game_record(path, game));
+
+    let result = match args[..] {
+        [] if best_of.is_some() => play_match(
+            &mut cli::Console::stdio(),
+            new_match(best_of.unwrap()), names.as_ref(), &ratings_file, change_decks,
+            |console, game, decks| cli::play(console, hotseat, decks, &rules, record_file(game).as_deref()),
+        ),
+        ["tui"] if best_of.is_some() => play_match(
+            &mut cli::Console::stdio(),
+            new_match(best_of.unwrap()), names.as_ref(), &ratings_file, change_decks,
+            |_, game, decks| tui::play(hotseat, decks, &rules, record_file(game).as_deref()),
+        ),
+        [] => {
+            cli::play(&mut cli::Console::stdio(), hotseat, &decks, &rules, record.as_deref()).and_then(rated)
+        }
+        ["tui"] => tui::play(hotseat, &decks, &rules, record.as_deref()).and_then(rated),
+        ["stats"] => stats(&ratings_file, None),
+        ["stats", name] => stats(&ratings_file, Some(name)),
+        ["leaderboard"] => leaderboard(&ratings_file),
+        ["tournament", path] => run_tournament(path, &ratings_file, hotseat),
+        ["trace", path] => trace(path),
+        ["tutorial"] if !hotseat => cli::tutorial(&mut cli::Console::stdio()),
+        ["puzzle", path] if !hotseat => {
+            load_puzzle(path).and_then(|p| cli::solve(&mut cli::Console::stdio(), &p))
+        }
+        ["server"] if !hotseat => {
+            net::serve(net::DEFAULT_ADDRESS, &decks, &rules, &ratings_file, caster.as_deref())
+        }
+        ["server", address] if !hotseat => {
+            net::serve(address, &decks, &rules, &ratings_file, caster.as_deref())
+        }
+        ["connect"] if !hotseat => {
+            net::connect(&mut cli::Console::stdio(), net::DEFAULT_ADDRESS, name.as_deref())
+        }
+        ["connect", address] if !hotseat => {
+            net::connect(&mut cli::Console::stdio(), address, name.as_deref())
+        }
+        ["spectate", game] | ["spectate", game, _] if !hotseat => {
+            let address = args.get(2).copied().unwrap_or(net::DEFAULT_ADDRESS);
+            match game.parse() {
+                Ok(game) => net::spectate(&mut cli::Console::stdio(), address, game, delay, caster.as_deref()),
+                Err(_) => {
+                    usage();
+                    std::process::exit(2);
                 }
             }
         }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{Face, is_straight};
-
-    #[test]
-    fn test_is_straight() {
-        assert_eq!(is_straight([Face::Two]), true);
-        assert_eq!(is_straight([Face::Two, Face::Three]), true);
-        assert_eq!(is_straight([Face::Two, Face::Two]), false);
-        assert_eq!(is_straight([Face::Two, Face::Four]), false);
-        assert_eq!(is_straight([Face::Three, Face::Four, Face::Five]), true);
-        assert_eq!(is_straight([Face::Three, Face::Four, Face::Six]), false);
-        assert_eq!(is_straight([Face::Three, Face::Four, Face::Four]), false);
-        assert_eq!(
-            is_straight([Face::Three, Face::Four, Face::Five, Face::Six]),
-            true,
-        );
-        assert_eq!(
-            is_straight([Face::Three, Face::Four, Face::Five, Face::Seven]),
-            false,
-        );
-        assert_eq!(
-            is_straight([Face::Two, Face::Four, Face::Five, Face::Six]),
-            false,
-        );
-        assert_eq!(
-            is_straight([Face::Three, Face::Four, Face::Four, Face::Five]),
-            false,
-        );
-        assert_eq!(
-            is_straight([Face::Three, Face::Four, Face::Four, Face::Six]),
-            false,
-        );
+        #[cfg(feature = "web")]
+        ["web"] if !hotseat => web::serve(web::DEFAULT_ADDRESS, &decks, &rules),
+        #[cfg(feature = "web")]
+        ["web", address] if !hotseat => web::serve(address, &decks, &rules),
+        _ => {
+            usage();
+            std::process::exit(2);
+        }
+    };
+    match result {
+        Ok(()) => {}
+        Err(Error::Exit) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+        }
     }
 }
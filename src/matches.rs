@@ -0,0 +1,266 @@
+//! Matches of several games between the same two participants
+//!
+//! A `Game` always starts with player 1, so the match seats the participant
+//! going first as player 1 of each game. Participants are numbered 0 and 1
+//! like players, participant 0 going first in the first game.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::deck::DeckList;
+use crate::game::{Outcome, PickCards};
+use crate::rules::RuleSet;
+
+/// Who goes first after the first game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirstPlayer {
+    Alternate,
+    /// The loser of the previous game picks who goes first, the participants
+    /// alternate after a draw
+    LoserChooses,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchError {
+    MatchOver,
+    GameInProgress,
+    NoGameInProgress,
+    /// Someone has to pick who goes first
+    ChoiceNeeded,
+    NotYourChoice,
+}
+
+impl std::fmt::Display for MatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            MatchError::MatchOver => write!(f, "The match is over"),
+            MatchError::GameInProgress => write!(f, "A game is in progress"),
+            MatchError::NoGameInProgress => write!(f, "No game is in progress"),
+            MatchError::ChoiceNeeded => write!(f, "The loser has to choose who goes first"),
+            MatchError::NotYourChoice => write!(f, "It's not your choice who goes first"),
+        }
+    }
+}
+
+impl std::error::Error for MatchError {}
+
+/// A game that was played, in terms of participants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchGame {
+    /// The participant who went first
+    pub first: u32,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    /// Maximum number of games, the match ends when a participant won more
+    /// than half of them
+    pub best_of: u32,
+    pub first_player: FirstPlayer,
+    pub rules: RuleSet,
+    decks: [DeckList; 2],
+    pub wins: [u32; 2],
+    pub draws: u32,
+    pub games: Vec<MatchGame>,
+    /// Who goes first in the next game, None while the loser chooses
+    next_first: Option<u32>,
+    /// The participant in each seat of the game in progress
+    playing: Option<[u32; 2]>,
+}
+
+impl Match {
+    pub fn new(decks: [DeckList; 2], rules: RuleSet, best_of: u32, first_player: FirstPlayer) -> Match {
+        Match {
+            best_of,
+            first_player,
+            rules,
+            decks,
+            wins: [0, 0],
+            draws: 0,
+            games: Vec::new(),
+            next_first: Some(0),
+            playing: None,
+        }
+    }
+
+    pub fn deck(&self, participant: u32) -> &DeckList {
+        &self.decks[participant as usize]
+    }
+
+    /// The result of the match, once it is over
+    pub fn outcome(&self) -> Option<Outcome> {
+        let needed = self.best_of / 2 + 1;
+        for participant in 0..2 {
+            if self.wins[participant as usize] >= needed {
+                return Some(Outcome::Win(participant));
+            }
+        }
+        if self.games.len() as u32 >= self.best_of {
+            return Some(match self.wins[0].cmp(&self.wins[1]) {
+                std::cmp::Ordering::Greater => Outcome::Win(0),
+                std::cmp::Ordering::Less => Outcome::Win(1),
+                std::cmp::Ordering::Equal => Outcome::Draw,
+            });
+        }
+        None
+    }
+
+    /// The participant who has to pick who goes first in the next game
+    pub fn chooser(&self) -> Option<u32> {
+        if self.next_first.is_some() || self.outcome().is_some() {
+            return None;
+        }
+        match self.games.last()?.outcome {
+            Outcome::Win(winner) => Some(1 - winner),
+            Outcome::Draw => None,
+        }
+    }
+
+    pub fn choose_first(&mut self, chooser: u32, first: u32) -> Result<(), MatchError> {
+        match self.chooser() {
+            None => Err(MatchError::NotYourChoice),
+            Some(c) if c != chooser => Err(MatchError::NotYourChoice),
+            Some(_) => {
+                self.next_first = Some(first);
+                Ok(())
+            }
+        }
+    }
+
+    /// Change a participant's deck, between games
+    pub fn change_deck(&mut self, participant: u32, deck: DeckList) -> Result<(), MatchError> {
+        if self.playing.is_some() {
+            return Err(MatchError::GameInProgress);
+        }
+        self.decks[participant as usize] = deck;
+        Ok(())
+    }
+
+    /// Start the next game
+    ///
+    /// Returns the participant in each seat, the first one going first.
+    pub fn next_game(&mut self) -> Result<[u32; 2], MatchError> {
+        if self.outcome().is_some() {
+            return Err(MatchError::MatchOver);
+        }
+        if self.playing.is_some() {
+            return Err(MatchError::GameInProgress);
+        }
+        let first = self.next_first.ok_or(MatchError::ChoiceNeeded)?;
+        let seats = [first, 1 - first];
+        self.playing = Some(seats);
+        Ok(seats)
+    }
+
+    /// The decks of the game in progress, by seat
+    pub fn seated_decks(&self) -> Result<[DeckList; 2], MatchError> {
+        let seats = self.playing.ok_or(MatchError::NoGameInProgress)?;
+        Ok(seats.map(|participant| self.decks[participant as usize].clone()))
+    }
+
+    /// Start the next game, dealing the starting hands
+    pub fn start_game<R: Rng>(&mut self, rng: &mut R) -> Result<([u32; 2], PickCards), MatchError> {
+        let seats = self.next_game()?;
+        let picks = PickCards::new(&self.seated_decks()?, self.rules.clone(), rng);
+        Ok((seats, picks))
+    }
+
+    /// Record the outcome of the game in progress, by seat
+    pub fn finish_game(&mut self, outcome: Outcome) -> Result<&MatchGame, MatchError> {
+        let seats = self.playing.take().ok_or(MatchError::NoGameInProgress)?;
+        let outcome = match outcome {
+            Outcome::Win(seat) => Outcome::Win(seats[seat as usize]),
+            Outcome::Draw => Outcome::Draw,
+        };
+        match outcome {
+            Outcome::Win(participant) => self.wins[participant as usize] += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+        self.next_first = match (self.first_player, outcome) {
+            (FirstPlayer::LoserChooses, Outcome::Win(_)) => None,
+            _ => Some(1 - seats[0]),
+        };
+        self.games.push(MatchGame { first: seats[0], outcome });
+        Ok(self.games.last().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::deck::DeckList;
+    use crate::game::{Face, Outcome, Suit};
+    use crate::rules::RuleSet;
+
+    use super::{FirstPlayer, Match, MatchError};
+
+    fn new_match(best_of: u32, first_player: FirstPlayer) -> Match {
+        let decks = [DeckList::standard(), DeckList::standard()];
+        Match::new(decks, RuleSet::default(), best_of, first_player)
+    }
+
+    #[test]
+    fn test_alternate() {
+        let mut m = new_match(3, FirstPlayer::Alternate);
+        assert_eq!(m.next_game(), Ok([0, 1]));
+        assert_eq!(m.next_game(), Err(MatchError::GameInProgress));
+        // Participant 1 wins from the second seat
+        m.finish_game(Outcome::Win(1)).unwrap();
+        assert_eq!(m.wins, [0, 1]);
+        assert_eq!(m.chooser(), None);
+
+        assert_eq!(m.next_game(), Ok([1, 0]));
+        // Participant 1 wins from the first seat
+        m.finish_game(Outcome::Win(0)).unwrap();
+        assert_eq!(m.wins, [0, 2]);
+        assert_eq!(m.outcome(), Some(Outcome::Win(1)));
+        assert_eq!(m.next_game(), Err(MatchError::MatchOver));
+        assert_eq!(m.games.iter().map(|g| g.first).collect::<Vec<_>>(), [0, 1]);
+    }
+
+    #[test]
+    fn test_loser_chooses() {
+        let mut m = new_match(3, FirstPlayer::LoserChooses);
+        m.next_game().unwrap();
+        m.finish_game(Outcome::Win(0)).unwrap();
+        assert_eq!(m.chooser(), Some(1));
+        assert_eq!(m.next_game(), Err(MatchError::ChoiceNeeded));
+        assert_eq!(m.choose_first(0, 0), Err(MatchError::NotYourChoice));
+        // Participant 1 lost and lets participant 0 go first again
+        m.choose_first(1, 0).unwrap();
+        assert_eq!(m.next_game(), Ok([0, 1]));
+        m.finish_game(Outcome::Draw).unwrap();
+        // Alternate after a draw
+        assert_eq!(m.chooser(), None);
+        assert_eq!(m.next_game(), Ok([1, 0]));
+        m.finish_game(Outcome::Win(0)).unwrap();
+        // Three games were played, 1 win each
+        assert_eq!((m.wins, m.draws), ([1, 1], 1));
+        assert_eq!(m.outcome(), Some(Outcome::Draw));
+        assert_eq!(m.chooser(), None);
+    }
+
+    #[test]
+    fn test_decks() {
+        let mut m = new_match(3, FirstPlayer::Alternate);
+        let red = DeckList { cards: vec![(Face::Jack, Suit::Hearts); 40] };
+        m.change_deck(1, red.clone()).unwrap();
+        assert_eq!(m.seated_decks(), Err(MatchError::NoGameInProgress));
+
+        let (seats, picks) = m.start_game(&mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(seats, [0, 1]);
+        assert!(picks.hand(1).iter().all(|c| c.face == Face::Jack && c.player == 1));
+        assert_eq!(m.change_deck(0, red.clone()), Err(MatchError::GameInProgress));
+        m.finish_game(Outcome::Win(0)).unwrap();
+
+        // The red deck follows participant 1 to the first seat
+        let (seats, picks) = m.start_game(&mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(seats, [1, 0]);
+        assert!(picks.hand(0).iter().all(|c| c.face == Face::Jack));
+        assert_eq!(m.deck(1), &red);
+    }
+}
@@ -0,0 +1,871 @@
+//! Playing over the network
+//!
+//! The server runs the game and each player connects to it with a client.
+//! Messages are JSON, one per line. The server only sends a player the
+//! information they are allowed to see.
+//!
+//! Spectators can also connect to watch a game by its number. They only see
+//! what is public, unless they are casters: with the token the server was
+//! given, they can ask for a delay and see everything, including the hands,
+//! but that many turns late. Cards stay in a hand for several turns, so a
+//! delay alone wouldn't keep players from spectating their own game to see
+//! the enemy's hand, and servers without a token don't allow it.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{SendError, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::Error;
+use crate::cli::{
+    Console, choose_action, read_yes_no, show_board, show_event, show_hand, show_outcome,
+};
+use superjack::deck::DeckList;
+use superjack::game::{
+    Action, Card, Event, Game, Outcome, Phase, PickCards, PlayerView, PublicView,
+    RuleError, SPECTATOR,
+};
+use superjack::rules::RuleSet;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:4321";
+
+/// Smallest delay casters can ask for, so what they show is a full round
+/// behind the game
+pub const MIN_DELAY: u32 = 2;
+
+/// Largest delay casters can ask for, since the server keeps the log
+pub const MAX_DELAY: u32 = 50;
+
+/// Longest message accepted, the biggest are the views sent to players
+const MAX_LINE: u64 = 64 * 1024;
+
+/// How long a new connection has to say whether it joins or spectates
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Sent to the first player of a game, while they wait for an opponent
+    Waiting { game: u64 },
+    Welcome { game: u64, player: u32 },
+    StartingHand { hand: Vec<Card>, can_mulligan: bool },
+    Events(Vec<Event>),
+    /// It is your turn to act
+    Prompt(Box<PlayerView>),
+    Refused(RuleError),
+    GameOver(Outcome),
+    /// Sent to spectators when they join
+    Watching { game: u64 },
+    NoSuchGame,
+    /// The delay asked for by a spectator isn't allowed
+    InvalidDelay { min: u32, max: u32 },
+    /// Only casters with the server's token can ask for a delay
+    NotCaster,
+    /// The board, sent to spectators at the start of each turn
+    Board(Box<PublicView>),
+    /// Both players' hands, only sent to casters
+    Hands([Vec<Card>; 2]),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// First message of a player, with their name if the game should be
+    /// rated
+    Join { name: Option<String> },
+    /// First message of a spectator, with the number of turns to wait to see
+    /// everything, or None to only see what is public
+    ///
+    /// Seeing everything takes the caster token of the server.
+    Spectate {
+        game: u64,
+        delay: Option<u32>,
+        #[serde(default)]
+        caster: Option<String>,
+    },
+    Mulligan(bool),
+    Action(Action),
+}
+
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Connection, Error> {
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send<T: Serialize>(&mut self, message: &T) -> Result<(), Error> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        Ok(())
+    }
+
+    pub fn receive<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        let mut line = String::new();
+        let read = (&mut self.reader).take(MAX_LINE).read_line(&mut line)?;
+        if read == 0 {
+            return Err(Error::Protocol("Connection closed".to_owned()));
+        }
+        if read as u64 == MAX_LINE && !line.ends_with('\n') {
+            return Err(Error::Protocol("Message too long".to_owned()));
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Whether the other side went away, for a connection that isn't
+    /// expected to send anything
+    fn is_closed(&self) -> bool {
+        if self.writer.set_nonblocking(true).is_err() {
+            return true;
+        }
+        let closed = match self.writer.peek(&mut [0]) {
+            Ok(0) => true,
+            Ok(_) => false,
+            Err(e) => e.kind() != std::io::ErrorKind::WouldBlock,
+        };
+        closed || self.writer.set_nonblocking(false).is_err()
+    }
+}
+
+struct Spectator {
+    /// Messages go through a thread per spectator, so a slow one doesn't hold
+    /// up the game
+    sender: Sender<ServerMessage>,
+    delay: Option<u32>,
+    /// Number of messages sent from the log
+    sent: usize,
+}
+
+/// The spectators of a game
+///
+/// Everything sent to them is logged along with the turn number, so people
+/// who join late can catch up, and delayed spectators get it when it's time.
+#[derive(Default)]
+pub struct Audience {
+    spectators: Vec<Spectator>,
+    turn: u32,
+    finished: bool,
+    /// Messages for spectators who see what is public
+    public: Vec<(u32, ServerMessage)>,
+    /// Messages for casters, who see everything
+    full: Vec<(u32, ServerMessage)>,
+}
+
+impl Audience {
+    pub fn add(&mut self, mut connection: Connection, delay: Option<u32>) {
+        let (sender, receiver) = channel::<ServerMessage>();
+        std::thread::spawn(move || {
+            for message in receiver {
+                if connection.send(&message).is_err() {
+                    break;
+                }
+            }
+        });
+        self.spectators.push(Spectator { sender, delay, sent: 0 });
+        self.flush();
+    }
+
+    /// Show the events that just happened
+    fn record(&mut self, game: &Game, events: &[Event]) {
+        let turns = events.iter().filter(|e| matches!(e, Event::TurnStarted { .. })).count();
+        self.turn += turns as u32;
+        let turn = self.turn;
+        let public = events.iter().map(|e| e.redact_for(SPECTATOR)).collect();
+        self.public.push((turn, ServerMessage::Events(public)));
+        self.full.push((turn, ServerMessage::Events(events.to_vec())));
+
+        // Show the board for each new turn, or when it went back
+        let undone = events.iter().any(|e| matches!(e, Event::Undone { .. }));
+        if turns > 0 || undone {
+            let board = ServerMessage::Board(Box::new(game.public_view()));
+            self.public.push((turn, board.clone()));
+            self.full.push((turn, board));
+            let hands = game.players.each_ref().map(|p| p.hand.clone());
+            self.full.push((turn, ServerMessage::Hands(hands)));
+        }
+        self.flush();
+    }
+
+    fn finish(&mut self, outcome: Outcome) {
+        self.public.push((self.turn, ServerMessage::GameOver(outcome)));
+        self.full.push((self.turn, ServerMessage::GameOver(outcome)));
+        self.finished = true;
+        self.flush();
+    }
+
+    /// Send spectators what they are allowed to see so far, dropping the
+    /// ones who left
+    fn flush(&mut self) {
+        let (turn, finished) = (self.turn, self.finished);
+        let (public, full) = (&self.public, &self.full);
+        self.spectators.retain_mut(|spectator| {
+            let log = match spectator.delay {
+                None => public,
+                Some(_) => full,
+            };
+            let delay = spectator.delay.unwrap_or(0);
+            while let Some((at, message)) = log.get(spectator.sent) {
+                if !finished && at + delay > turn {
+                    break;
+                }
+                if spectator.sender.send(message.clone()).is_err() {
+                    return false;
+                }
+                spectator.sent += 1;
+            }
+            true
+        });
+    }
+}
+
+/// State shared by the threads of the server
+struct Server {
+    decks: [DeckList; 2],
+    rules: RuleSet,
+    /// Where the results of games between named players are recorded, locked
+    /// while writing it
+    ratings: Mutex<String>,
+    /// The token casters give to see everything, nobody can without one
+    caster: Option<String>,
+    /// The audience of each game that is being played
+    games: Mutex<HashMap<u64, Arc<Mutex<Audience>>>>,
+    lobby: Mutex<Lobby>,
+}
+
+#[derive(Default)]
+struct Lobby {
+    /// The last game number
+    game: u64,
+    /// The player waiting for an opponent: their game, their connection to
+    /// check they are still there, and where to send the opponent's
+    /// connection and name
+    waiting: Option<(u64, Connection, Sender<Joining>)>,
+}
+
+/// A player joining a game, with their name if it should be rated
+type Joining = (Connection, Option<String>);
+
+/// Accept players and host games, two players at a time
+///
+/// The first player to join each game uses the first deck. Games between
+/// players who both gave a name are rated. Spectators who give the `caster`
+/// token can see everything, with a delay.
+pub fn serve<A: ToSocketAddrs>(
+    address: A,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    ratings: &str,
+    caster: Option<&str>,
+) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)?;
+    println!("Listening on {}", listener.local_addr()?);
+    accept(listener, decks, rules, ratings, caster)
+}
+
+fn accept(
+    listener: TcpListener,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    ratings: &str,
+    caster: Option<&str>,
+) -> Result<(), Error> {
+    let server = Arc::new(Server {
+        decks: decks.clone(),
+        rules: rules.clone(),
+        ratings: Mutex::new(ratings.to_owned()),
+        caster: caster.map(str::to_owned),
+        games: Default::default(),
+        lobby: Default::default(),
+    });
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                println!("Error accepting a connection: {}", e);
+                continue;
+            }
+        };
+        // Each connection says what it wants from its own thread, so one
+        // that stays quiet doesn't keep others from joining
+        let server = server.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = welcome(&server, stream) {
+                println!("Invalid connection: {}", e);
+            }
+        });
+    }
+}
+
+/// Read the first message of a connection, and have it join or watch a game
+///
+/// The thread of the first player of a game waits for an opponent, then goes
+/// on to host it.
+fn welcome(server: &Server, stream: TcpStream) -> Result<(), Error> {
+    let peer = stream.peer_addr()?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut connection = Connection::new(stream)?;
+    let message = connection.receive()?;
+    connection.writer.set_read_timeout(None)?;
+    match message {
+        ClientMessage::Join { mut name } => {
+            let mut lobby = server.lobby.lock().unwrap();
+            while let Some((game, first, opponent)) = lobby.waiting.take() {
+                // Players who left while waiting make room for this one
+                if first.is_closed() {
+                    server.games.lock().unwrap().remove(&game);
+                    println!("Player 1 of game {} left", game);
+                    continue;
+                }
+                // The first player's thread hosts the game
+                drop(lobby);
+                println!("Player 2 of game {} connected from {}", game, peer);
+                match opponent.send((connection, name)) {
+                    Ok(()) => return Ok(()),
+                    Err(SendError(returned)) => (connection, name) = returned,
+                }
+                server.games.lock().unwrap().remove(&game);
+                lobby = server.lobby.lock().unwrap();
+            }
+            lobby.game += 1;
+            let game = lobby.game;
+            let (sender, receiver) = channel();
+            let first = Connection::new(connection.writer.try_clone()?)?;
+            lobby.waiting = Some((game, first, sender));
+            server.games.lock().unwrap().insert(game, Default::default());
+            drop(lobby);
+            println!("Player 1 of game {} connected from {}", game, peer);
+            connection.send(&ServerMessage::Waiting { game })?;
+            // Nobody comes if the player left, and the next one took their
+            // place
+            let Ok((second, second_name)) = receiver.recv() else {
+                return Ok(());
+            };
+
+            let audience = server.games.lock().unwrap()[&game].clone();
+            let mut rng = StdRng::from_entropy();
+            let result = host_game([connection, second], game, &server.decks, &server.rules, &audience, &mut rng);
+            server.games.lock().unwrap().remove(&game);
+            match result {
+                Ok(outcome) => {
+                    println!("Game {} over", game);
+                    if let (Some(name), Some(second_name)) = (name, second_name) {
+                        if name != second_name {
+                            let ratings = server.ratings.lock().unwrap();
+                            crate::rate(&ratings, &[name, second_name], outcome)?;
+                        }
+                    }
+                }
+                Err(e) => println!("Game {} aborted: {}", game, e),
+            }
+        }
+        ClientMessage::Spectate { game, delay, caster } => {
+            if delay.is_some() && (server.caster.is_none() || caster != server.caster) {
+                connection.send(&ServerMessage::NotCaster)?;
+                return Ok(());
+            }
+            if delay.is_some_and(|d| !(MIN_DELAY..=MAX_DELAY).contains(&d)) {
+                connection.send(&ServerMessage::InvalidDelay { min: MIN_DELAY, max: MAX_DELAY })?;
+                return Ok(());
+            }
+            let audience = server.games.lock().unwrap().get(&game).cloned();
+            let Some(audience) = audience else {
+                return connection.send(&ServerMessage::NoSuchGame);
+            };
+            println!("Spectator of game {} connected from {}", game, peer);
+            connection.send(&ServerMessage::Watching { game })?;
+            audience.lock().unwrap().add(connection, delay);
+        }
+        m => return Err(Error::Protocol(format!("Unexpected message {:?}", m))),
+    }
+    Ok(())
+}
+
+/// Have a player pick their starting hand, while the other player does the
+/// same from another thread
+fn pick_starting_hand<R: Rng>(
+    connection: &mut Connection,
+    player: u32,
+    picks: &Mutex<PickCards>,
+    rng: &Mutex<&mut R>,
+) -> Result<(), Error> {
+    loop {
+        let (hand, can_mulligan) = {
+            let picks = picks.lock().unwrap();
+            (picks.hand(player).to_vec(), picks.can_mulligan(player))
+        };
+        connection.send(&ServerMessage::StartingHand { hand, can_mulligan })?;
+        if !can_mulligan {
+            let mut picks = picks.lock().unwrap();
+            if !picks.is_ready(player) {
+                picks.accept(player).unwrap();
+            }
+            return Ok(());
+        }
+        match connection.receive()? {
+            ClientMessage::Mulligan(false) => {
+                picks.lock().unwrap().accept(player).unwrap();
+                return Ok(());
+            }
+            ClientMessage::Mulligan(true) => {
+                let mut rng = rng.lock().unwrap();
+                picks.lock().unwrap().mulligan(player, &mut **rng).unwrap();
+            }
+            _ => return Err(Error::Protocol("Expected Mulligan".to_owned())),
+        }
+    }
+}
+
+/// Run a game between two connected players, in front of an audience
+pub fn host_game<R: Rng + Send>(
+    mut connections: [Connection; 2],
+    game: u64,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    audience: &Mutex<Audience>,
+    rng: &mut R,
+) -> Result<Outcome, Error> {
+    for (player, connection) in connections.iter_mut().enumerate() {
+        connection.send(&ServerMessage::Welcome { game, player: player as u32 })?;
+    }
+
+    // Both players pick their starting hand at the same time
+    let picks = Mutex::new(PickCards::new(decks, rules.clone(), rng));
+    let rng = Mutex::new(rng);
+    let streams = connections.iter()
+        .map(|c| c.writer.try_clone())
+        .collect::<Result<Vec<_>, _>>()?;
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = connections.iter_mut().enumerate()
+            .map(|(player, connection)| {
+                let (picks, rng, streams) = (&picks, &rng, &streams);
+                scope.spawn(move || {
+                    let result = pick_starting_hand(connection, player as u32, picks, rng);
+                    if result.is_err() {
+                        // Don't leave the other player waiting
+                        for stream in streams {
+                            let _ = stream.shutdown(Shutdown::Both);
+                        }
+                    }
+                    result
+                })
+            })
+            .collect();
+        threads.into_iter().try_for_each(|thread| thread.join().unwrap())
+    })?;
+    let mut game = picks.into_inner().unwrap().start().unwrap();
+    let mut events = game.start();
+
+    loop {
+        for (player, connection) in connections.iter_mut().enumerate() {
+            let visible = events.iter().map(|e| e.redact_for(player as u32)).collect();
+            connection.send(&ServerMessage::Events(visible))?;
+        }
+        audience.lock().unwrap().record(&game, &events);
+        if let Some(outcome) = game.outcome() {
+            for connection in &mut connections {
+                connection.send(&ServerMessage::GameOver(outcome))?;
+            }
+            audience.lock().unwrap().finish(outcome);
+            return Ok(outcome);
+        }
+
+        // Pass without asking if there is nothing else to do
+        let player = game.priority();
+        if game.phase != Phase::Main && game.legal_actions(player) == [Action::Pass] {
+            events = game.apply(player, Action::Pass).unwrap();
+            continue;
+        }
+
+        // Ask the player until they pick an action that's allowed
+        let connection = &mut connections[player as usize];
+        events = loop {
+            connection.send(&ServerMessage::Prompt(Box::new(game.view_for(player))))?;
+            let action = match connection.receive()? {
+                ClientMessage::Action(action) => action,
+                m => return Err(Error::Protocol(format!("Unexpected message {:?}", m))),
+            };
+            match game.apply(player, action) {
+                Ok(events) => break events,
+                Err(e) => connection.send(&ServerMessage::Refused(e))?,
+            }
+        };
+    }
+}
+
+/// Connect to a server and play, under the given name to have the game rated
+pub fn connect<A: ToSocketAddrs>(console: &mut Console, address: A, name: Option<&str>) -> Result<(), Error> {
+    let mut connection = Connection::new(TcpStream::connect(address)?)?;
+    connection.send(&ClientMessage::Join { name: name.map(str::to_owned) })?;
+    let mut player = 0;
+    // Actions the player gave in advance
+    let mut queued = VecDeque::new();
+    loop {
+        match connection.receive()? {
+            ServerMessage::Waiting { game } => {
+                println!("Waiting for an opponent to join game {}...", game);
+            }
+            ServerMessage::Welcome { game, player: p } => {
+                player = p;
+                println!("Connected as player {} of game {}", player + 1, game);
+            }
+            ServerMessage::StartingHand { hand, can_mulligan } => {
+                println!("\nYou draw cards:");
+                show_hand(console, &hand, player)?;
+                if can_mulligan {
+                    let mulligan = read_yes_no(console, "Mulligan?", Some(false))?;
+                    connection.send(&ClientMessage::Mulligan(mulligan))?;
+                    if !mulligan {
+                        println!("Waiting for the other player...");
+                    }
+                }
+            }
+            ServerMessage::Events(events) => {
+                for event in &events {
+                    show_event(console, event, player)?;
+                }
+            }
+            ServerMessage::Prompt(status) => {
+                if queued.is_empty() {
+                    queued.extend(choose_action(console, &status)?);
+                }
+                let action = queued.pop_front().unwrap();
+                connection.send(&ClientMessage::Action(action))?;
+            }
+            ServerMessage::Refused(e) => {
+                println!("{}", e);
+                queued.clear();
+            }
+            ServerMessage::GameOver(outcome) => {
+                show_outcome(console, outcome)?;
+                return Ok(());
+            }
+            m => return Err(Error::Protocol(format!("Unexpected message {:?}", m))),
+        }
+    }
+}
+
+/// Connect to a server and watch a game, seeing everything `delay` turns late
+/// if given, which takes the server's `caster` token
+pub fn spectate<A: ToSocketAddrs>(
+    console: &mut Console,
+    address: A,
+    game: u64,
+    delay: Option<u32>,
+    caster: Option<&str>,
+) -> Result<(), Error> {
+    let mut connection = Connection::new(TcpStream::connect(address)?)?;
+    let caster = caster.map(str::to_owned);
+    connection.send(&ClientMessage::Spectate { game, delay, caster })?;
+    loop {
+        match connection.receive()? {
+            ServerMessage::Watching { game } => match delay {
+                None => println!("Watching game {}", game),
+                Some(delay) => println!("Watching game {}, {} turns late", game, delay),
+            },
+            ServerMessage::NoSuchGame => {
+                return Err(Error::Protocol(format!("There is no game {}", game)));
+            }
+            ServerMessage::InvalidDelay { min, max } => {
+                return Err(Error::Protocol(format!("The delay has to be between {} and {} turns", min, max)));
+            }
+            ServerMessage::NotCaster => {
+                return Err(Error::Protocol("Seeing everything takes the server's caster token".to_owned()));
+            }
+            ServerMessage::Events(events) => {
+                for event in &events {
+                    show_event(console, event, SPECTATOR)?;
+                }
+            }
+            ServerMessage::Board(view) => show_board(console, &view)?,
+            ServerMessage::Hands(hands) => {
+                for (player, hand) in hands.iter().enumerate() {
+                    println!("Player {}'s cards:", player + 1);
+                    show_hand(console, hand, player as u32)?;
+                }
+            }
+            ServerMessage::GameOver(outcome) => {
+                show_outcome(console, outcome)?;
+                return Ok(());
+            }
+            m => return Err(Error::Protocol(format!("Unexpected message {:?}", m))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Mutex;
+
+    use super::{
+        Audience, ClientMessage, Connection, MAX_LINE, MIN_DELAY, MAX_DELAY, ServerMessage, accept,
+        host_game,
+    };
+    use crate::Error;
+    use superjack::deck::DeckList;
+    use superjack::game::{Action, Event, Game, Outcome, Phase, Player, deal};
+    use superjack::rules::RuleSet;
+
+    /// Make a connected pair of connections
+    fn pair() -> (Connection, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let server = listener.accept().unwrap().0;
+        (Connection::new(server).unwrap(), Connection::new(client).unwrap())
+    }
+
+    #[test]
+    fn test_message_too_long() {
+        let (mut server, mut client) = pair();
+        let long = vec![b' '; MAX_LINE as usize];
+        client.writer.write_all(&long).unwrap();
+        client.send(&ClientMessage::Mulligan(false)).unwrap();
+        assert!(matches!(server.receive::<ClientMessage>(), Err(Error::Protocol(_))));
+
+        // Messages up to the limit are fine
+        let mut line = serde_json::to_vec(&ClientMessage::Mulligan(true)).unwrap();
+        line.resize(MAX_LINE as usize - 1, b' ');
+        line.push(b'\n');
+        let (mut server, mut client) = pair();
+        client.writer.write_all(&line).unwrap();
+        assert!(matches!(server.receive().unwrap(), ClientMessage::Mulligan(true)));
+    }
+
+    #[test]
+    fn test_hidden_information() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let first = Connection::new(listener.accept().unwrap().0).unwrap();
+            let second = Connection::new(listener.accept().unwrap().0).unwrap();
+            let decks = [DeckList::standard(), DeckList::standard()];
+            let audience = Mutex::new(Audience::default());
+            let mut rng = StdRng::seed_from_u64(42);
+            host_game([first, second], 1, &decks, &RuleSet::default(), &audience, &mut rng)
+        });
+
+        let mut clients = [
+            Connection::new(TcpStream::connect(address).unwrap()).unwrap(),
+            Connection::new(TcpStream::connect(address).unwrap()).unwrap(),
+        ];
+        for (player, client) in clients.iter_mut().enumerate() {
+            match client.receive().unwrap() {
+                ServerMessage::Welcome { game: 1, player: p } => assert_eq!(p, player as u32),
+                m => panic!("{:?}", m),
+            }
+        }
+        for client in &mut clients {
+            match client.receive().unwrap() {
+                ServerMessage::StartingHand { hand, can_mulligan: true } => {
+                    assert_eq!(hand.len(), 5);
+                }
+                m => panic!("{:?}", m),
+            }
+            client.send(&ClientMessage::Mulligan(false)).unwrap();
+        }
+
+        // Player 1 draws, player 2 is told they did but not what
+        match clients[0].receive().unwrap() {
+            ServerMessage::Events(events) => {
+                assert!(matches!(events[1], Event::CardDrawn { player: 0, card: Some(_) }));
+            }
+            m => panic!("{:?}", m),
+        }
+        match clients[1].receive().unwrap() {
+            ServerMessage::Events(events) => {
+                assert_eq!(events[1], Event::CardDrawn { player: 0, card: None });
+            }
+            m => panic!("{:?}", m),
+        }
+
+        // Player 1 is prompted, and only sees the size of the enemy's hand
+        match clients[0].receive().unwrap() {
+            ServerMessage::Prompt(status) => {
+                assert_eq!(status.phase, Phase::Main);
+                assert_eq!(status.hand.len(), 6);
+                assert_eq!(status.enemy_hand, 5);
+            }
+            m => panic!("{:?}", m),
+        }
+
+        // Invalid actions are refused, and the player is asked again
+        clients[0].send(&ClientMessage::Action(Action::Block { creature: 0, attacker: 0 })).unwrap();
+        match clients[0].receive().unwrap() {
+            ServerMessage::Refused(_) => {}
+            m => panic!("{:?}", m),
+        }
+        match clients[0].receive().unwrap() {
+            ServerMessage::Prompt(_) => {}
+            m => panic!("{:?}", m),
+        }
+        clients[0].send(&ClientMessage::Action(Action::Pass)).unwrap();
+        for client in &mut clients {
+            match client.receive().unwrap() {
+                ServerMessage::Events(events) => {
+                    assert_eq!(events, vec![Event::PhaseChanged { phase: Phase::Attack }]);
+                }
+                m => panic!("{:?}", m),
+            }
+        }
+
+        // Disconnecting ends the game
+        drop(clients);
+        assert!(server.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_spectators() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let (library0, hand0) = deal(0, &mut rng);
+        let (library1, hand1) = deal(1, &mut rng);
+        let mut game = Game::new([Player::new(library0, hand0), Player::new(library1, hand1)]);
+
+        let mut audience = Audience::default();
+        let (live, mut live_client) = pair();
+        let (delayed, mut delayed_client) = pair();
+        audience.add(live, None);
+        audience.add(delayed, Some(MIN_DELAY));
+        let events = game.start();
+        audience.record(&game, &events);
+
+        // Live spectators see the board, but not the cards drawn
+        match live_client.receive().unwrap() {
+            ServerMessage::Events(events) => {
+                assert_eq!(events[1], Event::CardDrawn { player: 0, card: None });
+            }
+            m => panic!("{:?}", m),
+        }
+        match live_client.receive().unwrap() {
+            ServerMessage::Board(view) => assert_eq!(view.players[0].hand, 6),
+            m => panic!("{:?}", m),
+        }
+
+        // Spectators who join late catch up
+        let (late, mut late_client) = pair();
+        audience.add(late, None);
+        assert!(matches!(late_client.receive().unwrap(), ServerMessage::Events(_)));
+        assert!(matches!(late_client.receive().unwrap(), ServerMessage::Board(_)));
+
+        // Delayed spectators see everything, MIN_DELAY turns late
+        let mut passes = 0;
+        let mut turns = 0;
+        while turns < MIN_DELAY {
+            let events = game.apply(game.priority(), Action::Pass).unwrap();
+            audience.record(&game, &events);
+            if turns == 0 {
+                passes += 1;
+            }
+            if events.iter().any(|e| matches!(e, Event::TurnStarted { .. })) {
+                turns += 1;
+            }
+        }
+        match delayed_client.receive().unwrap() {
+            ServerMessage::Events(events) => {
+                assert!(matches!(events[1], Event::CardDrawn { player: 0, card: Some(_) }));
+            }
+            m => panic!("{:?}", m),
+        }
+        assert!(matches!(delayed_client.receive().unwrap(), ServerMessage::Board(_)));
+        match delayed_client.receive().unwrap() {
+            ServerMessage::Hands(hands) => assert_eq!(hands[0].len(), 6),
+            m => panic!("{:?}", m),
+        }
+        // Passing through the phases of the first turn, but not the last pass
+        // which started the second one
+        for _ in 1..passes {
+            assert!(matches!(delayed_client.receive().unwrap(), ServerMessage::Events(_)));
+        }
+
+        // Everything is sent once the game is over
+        audience.finish(Outcome::Draw);
+        match delayed_client.receive().unwrap() {
+            ServerMessage::Events(events) => {
+                assert!(matches!(events[1], Event::CardDrawn { player: 1, card: Some(_) }));
+            }
+            m => panic!("{:?}", m),
+        }
+        assert!(matches!(delayed_client.receive().unwrap(), ServerMessage::Board(_)));
+        assert!(matches!(delayed_client.receive().unwrap(), ServerMessage::Hands(_)));
+        while !matches!(delayed_client.receive().unwrap(), ServerMessage::GameOver(Outcome::Draw)) {}
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let decks = [DeckList::standard(), DeckList::standard()];
+            accept(listener, &decks, &RuleSet::default(), "", Some("secret"))
+        });
+        let connect = || Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+
+        // A connection that says nothing doesn't keep players from joining
+        let _idle = connect();
+        let mut players = [connect(), connect()];
+        players[0].send(&ClientMessage::Join { name: None }).unwrap();
+        assert!(matches!(players[0].receive().unwrap(), ServerMessage::Waiting { game: 1 }));
+        players[1].send(&ClientMessage::Join { name: None }).unwrap();
+        for connection in &mut players {
+            assert!(matches!(connection.receive().unwrap(), ServerMessage::Welcome { game: 1, .. }));
+        }
+
+        // Only casters can see everything, and not live or too late
+        let spectate = |delay, caster: Option<&str>| {
+            let mut spectator = connect();
+            let caster = caster.map(str::to_owned);
+            spectator.send(&ClientMessage::Spectate { game: 1, delay, caster }).unwrap();
+            spectator.receive().unwrap()
+        };
+        for caster in [None, Some("guess")] {
+            assert!(matches!(spectate(Some(MIN_DELAY), caster), ServerMessage::NotCaster));
+        }
+        for delay in [0, MIN_DELAY - 1, MAX_DELAY + 1] {
+            assert!(matches!(spectate(Some(delay), Some("secret")), ServerMessage::InvalidDelay { .. }));
+        }
+        assert!(matches!(spectate(Some(MIN_DELAY), Some("secret")), ServerMessage::Watching { game: 1 }));
+        assert!(matches!(spectate(None, None), ServerMessage::Watching { game: 1 }));
+    }
+
+    #[test]
+    fn test_waiting_player_left() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let decks = [DeckList::standard(), DeckList::standard()];
+            accept(listener, &decks, &RuleSet::default(), "", None)
+        });
+        let connect = || Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+
+        // The first player gives up before anyone joins
+        let mut gone = connect();
+        gone.send(&ClientMessage::Join { name: None }).unwrap();
+        assert!(matches!(gone.receive().unwrap(), ServerMessage::Waiting { game: 1 }));
+        drop(gone);
+
+        // The next player waits in their place, and plays the one after
+        let mut players = [connect(), connect()];
+        players[0].send(&ClientMessage::Join { name: None }).unwrap();
+        assert!(matches!(players[0].receive().unwrap(), ServerMessage::Waiting { game: 2 }));
+        players[1].send(&ClientMessage::Join { name: None }).unwrap();
+        for (player, connection) in players.iter_mut().enumerate() {
+            match connection.receive().unwrap() {
+                ServerMessage::Welcome { game, player: p } => assert_eq!((game, p), (2, player as u32)),
+                m => panic!("Unexpected message {:?}", m),
+            }
+        }
+    }
+}
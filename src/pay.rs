@@ -0,0 +1,225 @@
+//! Choosing which gems to tap when paying for a card
+//!
+//! The interfaces use this to suggest a payment that the player can accept
+//! or change, and an AI can use it to pay without having to think about it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Color, Face, Gem, is_straight};
+
+/// What to look for in a payment, from the most important to the least
+///
+/// These are set in the `payment` table of the rule set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preferences {
+    /// Pay with a straight, so the creature gets Royal Charge
+    pub royal_charge: bool,
+    /// Leave the longest possible straight in the untapped gems, to get Royal
+    /// Charge later in the turn
+    pub keep_straight: bool,
+    /// Tap the lowest gems, keeping the highest ones to sacrifice for mining
+    pub keep_high: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            royal_charge: true,
+            keep_straight: true,
+            keep_high: true,
+        }
+    }
+}
+
+/// Length of the longest straight that can be made from these faces
+fn longest_straight<I: IntoIterator<Item=Face>>(faces: I) -> usize {
+    let mut faces: Vec<Face> = faces.into_iter().collect();
+    faces.sort_unstable();
+    faces.dedup();
+    let mut longest = 0;
+    let mut length = 0;
+    let mut prev_face: Option<Face> = None;
+    for face in faces {
+        length = match prev_face {
+            Some(prev) if prev.next() == Some(face) => length + 1,
+            _ => 1,
+        };
+        longest = longest.max(length);
+        prev_face = Some(face);
+    }
+    longest
+}
+
+/// How good a payment is, to compare them
+fn score(
+    gems: &[Gem],
+    available: &[usize],
+    picked: &[usize],
+    royal_charge: bool,
+    preferences: &Preferences,
+) -> (bool, usize, i32) {
+    let royal_charge = preferences.royal_charge && royal_charge;
+    let straight_left = match preferences.keep_straight {
+        true => longest_straight(
+            available.iter()
+                .filter(|g| !picked.contains(g))
+                .map(|&g| gems[g].card.face),
+        ),
+        false => 0,
+    };
+    let low = match preferences.keep_high {
+        true => picked.iter().map(|&g| -(gems[g].card.face as i32)).sum(),
+        false => 0,
+    };
+    (royal_charge, straight_left, low)
+}
+
+/// Every straight of `length` faces in the available gems, with one gem per
+/// face
+///
+/// `available` has to be sorted by face.
+fn straights(gems: &[Gem], available: &[usize], length: usize) -> Vec<Vec<usize>> {
+    let mut distinct = available.to_vec();
+    distinct.dedup_by_key(|g| gems[*g].card.face);
+    distinct.windows(length)
+        .filter(|w| is_straight(w.iter().map(|&g| gems[g].card.face)))
+        .map(|w| w.to_vec())
+        .collect()
+}
+
+/// Suggest the gems to tap to pay `cost` with gems of `color`
+///
+/// Returns the positions of the gems, or None if there are not enough
+/// untapped gems of that color.
+pub fn suggest(gems: &[Gem], cost: u32, color: Color, preferences: &Preferences) -> Option<Vec<usize>> {
+    let mut available: Vec<usize> = gems.iter().enumerate()
+        .filter(|(_, g)| !g.tapped && g.card.color() == color)
+        .map(|(i, _)| i)
+        .collect();
+    available.sort_by_key(|&g| gems[g].card.face);
+    let cost = cost as usize;
+    if available.len() < cost {
+        return None;
+    }
+
+    // Rather than trying every payment, only compare the straights that give
+    // Royal Charge, or else the lowest gems around each straight that can be
+    // kept: the other payments can't score better
+    let mut candidates = Vec::new();
+    if preferences.royal_charge && cost >= 2 {
+        candidates = straights(gems, &available, cost);
+    }
+    let royal_charge = !candidates.is_empty();
+    if !royal_charge {
+        let keep = match preferences.keep_straight {
+            true => longest_straight(available.iter().map(|&g| gems[g].card.face))
+                .min(available.len() - cost),
+            false => 0,
+        };
+        let kept = match keep {
+            0 => vec![Vec::new()],
+            _ => straights(gems, &available, keep),
+        };
+        candidates = kept.into_iter()
+            .map(|kept| {
+                available.iter()
+                    .filter(|g| !kept.contains(g))
+                    .take(cost)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+    }
+    let mut picked = candidates.into_iter()
+        .max_by_key(|picked| score(gems, &available, picked, royal_charge, preferences))?;
+    picked.sort_unstable();
+    Some(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::game::{Card, Color, Face, Gem, Suit, check_payment, legal_payments};
+
+    use super::{Preferences, longest_straight, score, suggest};
+
+    fn gems(faces: &[Face]) -> Vec<Gem> {
+        faces.iter()
+            .map(|&face| Gem { card: Card { player: 0, suit: Suit::Hearts, face }, tapped: false })
+            .collect()
+    }
+
+    #[test]
+    fn test_longest_straight() {
+        assert_eq!(longest_straight([]), 0);
+        assert_eq!(longest_straight([Face::Four]), 1);
+        assert_eq!(longest_straight([Face::Two, Face::Four, Face::Three, Face::Three]), 3);
+        assert_eq!(longest_straight([Face::Two, Face::Four, Face::Five, Face::Six]), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let all = Preferences::default();
+        let none = Preferences { royal_charge: false, keep_straight: false, keep_high: false };
+
+        // Royal Charge comes first
+        let hand = gems(&[Face::Two, Face::Four, Face::Five, Face::Seven]);
+        assert_eq!(suggest(&hand, 2, Color::Red, &all), Some(vec![1, 2]));
+        let low = Preferences { royal_charge: false, ..all.clone() };
+        assert_eq!(suggest(&hand, 2, Color::Red, &low), Some(vec![0, 3]));
+
+        // Keep 3-4-5 for later, rather than the low 2
+        let hand = gems(&[Face::Two, Face::Three, Face::Four, Face::Five, Face::Seven]);
+        let keep = Preferences { royal_charge: false, ..all.clone() };
+        assert_eq!(suggest(&hand, 2, Color::Red, &keep), Some(vec![0, 4]));
+        let cheap = Preferences { keep_straight: false, ..keep.clone() };
+        assert_eq!(suggest(&hand, 2, Color::Red, &cheap), Some(vec![0, 1]));
+
+        // Without preferences, any payment will do
+        assert_eq!(suggest(&hand, 2, Color::Red, &none).map(|g| g.len()), Some(2));
+
+        // Only untapped gems of the right color
+        let mut hand = gems(&[Face::Two, Face::Three, Face::Four]);
+        hand[1].tapped = true;
+        assert_eq!(suggest(&hand, 2, Color::Red, &all), Some(vec![0, 2]));
+        assert_eq!(suggest(&hand, 3, Color::Red, &all), None);
+        assert_eq!(suggest(&hand, 1, Color::Black, &all), None);
+    }
+
+    #[test]
+    fn test_best() {
+        // The suggestion is as good as the best of every payment
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..300 {
+            let mut hand: Vec<Gem> = (0..rng.gen_range(0..9))
+                .map(|_| {
+                    let face = Face::all()[rng.gen_range(0..6)];
+                    let suit = if rng.gen_bool(0.8) { Suit::Hearts } else { Suit::Spades };
+                    Gem { card: Card { player: 0, suit, face }, tapped: rng.gen_bool(0.2) }
+                })
+                .collect();
+            hand.sort_by_key(|g| (g.card.color(), g.card.face));
+            let available: Vec<usize> = (0..hand.len())
+                .filter(|&g| !hand[g].tapped && hand[g].card.color() == Color::Red)
+                .collect();
+            for cost in 0..5 {
+                for royal_charge in [false, true] {
+                    for keep_straight in [false, true] {
+                        let preferences = Preferences { royal_charge, keep_straight, keep_high: true };
+                        let best = legal_payments(&hand, cost, Color::Red).into_iter()
+                            .map(|p| score(&hand, &available, &p.gems, p.royal_charge, &preferences))
+                            .max();
+                        let suggested = suggest(&hand, cost, Color::Red, &preferences).map(|picked| {
+                            let payment = check_payment(&hand, &picked, cost, Color::Red).unwrap();
+                            score(&hand, &available, &picked, payment.royal_charge, &preferences)
+                        });
+                        assert_eq!(suggested, best, "cost {} gems {:?}", cost, hand);
+                    }
+                }
+            }
+        }
+    }
+}
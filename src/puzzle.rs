@@ -0,0 +1,335 @@
+//! "Win this turn" puzzles
+//!
+//! A puzzle is a position at the start of player 1's turn, who has to bring
+//! the enemy's life to 0 before the turn ends, whatever the enemy does. It is
+//! read from TOML, with cards written like in deck lists:
+//!
+//! ```toml
+//! title = "Through the wall"
+//! description = "The enemy has a single Jack to block with."
+//! enemy_life = 6
+//! creatures = ["K♥", "Q♥", "J♠"]
+//! enemy_creatures = ["J♣"]
+//!
+//! [rules.strengths]
+//! jack = 2
+//! queen = 4
+//! king = 5
+//! ace = 3
+//! ```
+//!
+//! The rules don't give combat strengths yet, so a puzzle has to set them as a
+//! house rule for its creatures to deal damage.
+//!
+//! Creatures can be stacked by listing several cards, such as `"J♥ J♦"`.
+//! The solver plays every line, with the enemy picking the best defense, to
+//! check that a puzzle has a solution.
+
+use serde::Deserialize;
+
+use crate::deck::parse_card;
+use crate::game::{Action, CreatureStatus, Game, Outcome, RuleError};
+use crate::rules::RuleSet;
+use crate::scenario::Scenario;
+
+/// The player solving the puzzle, the enemy is player 2
+pub const PLAYER: u32 = 0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Puzzle {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub rules: RuleSet,
+    /// The player's life, the rules' starting life if not given
+    pub life: Option<i32>,
+    pub enemy_life: i32,
+    #[serde(default)]
+    pub hand: String,
+    #[serde(default)]
+    pub gems: String,
+    #[serde(default)]
+    pub tapped_gems: String,
+    /// Creatures that are ready to attack
+    #[serde(default)]
+    pub creatures: Vec<String>,
+    /// Creatures that were summoned this turn
+    #[serde(default)]
+    pub untrained_creatures: Vec<String>,
+    #[serde(default)]
+    pub enemy_creatures: Vec<String>,
+    /// Enemy creatures that can't block
+    #[serde(default)]
+    pub tapped_enemy_creatures: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleError {
+    Syntax(String),
+    InvalidCard(String),
+    EmptyCreature,
+    /// The rules have no strengths, so combat can't win the puzzle
+    NoStrengths,
+}
+
+impl std::fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            PuzzleError::Syntax(ref e) => write!(f, "{}", e),
+            PuzzleError::InvalidCard(ref text) => write!(f, "Invalid card: {}", text),
+            PuzzleError::EmptyCreature => write!(f, "A creature has no card"),
+            PuzzleError::NoStrengths => {
+                write!(f, "Combat deals no damage without [rules.strengths], the puzzle couldn't be won")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+/// Where the player is in the puzzle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    Playing,
+    Solved,
+    /// The turn is over, or the player lost
+    Failed,
+}
+
+impl Puzzle {
+    pub fn from_toml(text: &str) -> Result<Puzzle, PuzzleError> {
+        let puzzle: Puzzle = toml::from_str(text).map_err(|e| PuzzleError::Syntax(e.to_string()))?;
+        let lists = [&puzzle.hand, &puzzle.gems, &puzzle.tapped_gems];
+        let creatures = puzzle.creatures.iter()
+            .chain(&puzzle.untrained_creatures)
+            .chain(&puzzle.enemy_creatures)
+            .chain(&puzzle.tapped_enemy_creatures);
+        for creature in creatures.clone() {
+            if creature.split_whitespace().next().is_none() {
+                return Err(PuzzleError::EmptyCreature);
+            }
+        }
+        for text in lists.into_iter().chain(creatures).flat_map(|l| l.split_whitespace()) {
+            if parse_card(text).is_none() {
+                return Err(PuzzleError::InvalidCard(text.to_owned()));
+            }
+        }
+        if puzzle.rules.strengths.is_none() {
+            return Err(PuzzleError::NoStrengths);
+        }
+        Ok(puzzle)
+    }
+
+    /// The starting position, in player 1's main phase
+    pub fn game(&self) -> Game {
+        // Taking back actions would make the solver go around in circles
+        let rules = RuleSet { undo: false, ..self.rules.clone() };
+        let mut scenario = Scenario::new()
+            .rules(rules)
+            .life(1, self.enemy_life)
+            .hand(PLAYER, &self.hand)
+            .gems(PLAYER, &self.gems)
+            .tapped_gems(PLAYER, &self.tapped_gems);
+        if let Some(life) = self.life {
+            scenario = scenario.life(PLAYER, life);
+        }
+        let creatures = [
+            (PLAYER, &self.creatures, CreatureStatus::Ready),
+            (PLAYER, &self.untrained_creatures, CreatureStatus::Untrained),
+            (1, &self.enemy_creatures, CreatureStatus::Ready),
+            (1, &self.tapped_enemy_creatures, CreatureStatus::Tapped),
+        ];
+        for (player, list, status) in creatures {
+            for cards in list {
+                scenario = scenario.creature(player, cards, status);
+            }
+        }
+        scenario.build()
+    }
+}
+
+pub fn progress(game: &Game) -> Progress {
+    match game.outcome() {
+        Some(Outcome::Win(PLAYER)) => Progress::Solved,
+        Some(_) => Progress::Failed,
+        None if game.current_player != PLAYER => Progress::Failed,
+        None => Progress::Playing,
+    }
+}
+
+/// The actions the player with priority can take, and where they lead
+fn moves(game: &Game) -> impl Iterator<Item=(Action, Game)> + '_ {
+    let player = game.priority();
+    game.legal_actions(player).into_iter()
+        .filter(|a| *a != Action::Undo)
+        .map(move |action| {
+            let mut next = game.clone();
+            next.apply(player, action.clone()).unwrap();
+            (action, next)
+        })
+}
+
+/// Whether the player can still win this turn, whatever the enemy does
+pub fn can_win(game: &Game) -> bool {
+    match progress(game) {
+        Progress::Solved => true,
+        Progress::Failed => false,
+        Progress::Playing if game.priority() == PLAYER => moves(game).any(|(_, next)| can_win(&next)),
+        Progress::Playing => moves(game).all(|(_, next)| can_win(&next)),
+    }
+}
+
+/// An action that keeps the win in reach, for the player
+pub fn winning_action(game: &Game) -> Option<Action> {
+    if progress(game) != Progress::Playing || game.priority() != PLAYER {
+        return None;
+    }
+    moves(game).find(|(_, next)| can_win(next)).map(|(action, _)| action)
+}
+
+/// The enemy's best answer: an action that stops the player from winning if
+/// there is one
+pub fn defense(game: &Game) -> Action {
+    let mut fallback = None;
+    for (action, next) in moves(game) {
+        if !can_win(&next) {
+            return action;
+        }
+        fallback.get_or_insert(action);
+    }
+    fallback.unwrap_or(Action::Pass)
+}
+
+/// A winning line, with the enemy defending as well as possible
+pub fn solution(game: &Game) -> Option<Vec<(u32, Action)>> {
+    let mut game = game.clone();
+    let mut line = Vec::new();
+    while progress(&game) == Progress::Playing {
+        let player = game.priority();
+        let action = if player == PLAYER { winning_action(&game)? } else { defense(&game) };
+        game.apply(player, action.clone()).unwrap();
+        line.push((player, action));
+    }
+    match progress(&game) {
+        Progress::Solved => Some(line),
+        _ => None,
+    }
+}
+
+/// Play the player's actions, with the enemy defending as well as possible
+///
+/// Returns whether the actions solve the puzzle.
+pub fn check(game: &Game, actions: &[Action]) -> Result<bool, RuleError> {
+    let mut game = game.clone();
+    let mut actions = actions.iter();
+    while progress(&game) == Progress::Playing {
+        let player = game.priority();
+        let action = if player == PLAYER {
+            match actions.next() {
+                Some(action) => action.clone(),
+                None => break,
+            }
+        } else {
+            defense(&game)
+        };
+        game.apply(player, action)?;
+    }
+    Ok(progress(&game) == Progress::Solved)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Action, Phase};
+
+    use super::{Progress, Puzzle, PuzzleError, can_win, check, progress, solution};
+
+    const WALL: &str = "\
+title = \"Through the wall\"
+enemy_life = 6
+creatures = [\"K♥\", \"Q♥\", \"J♠\"]
+enemy_creatures = [\"J♣\"]
+
+[rules.strengths]
+jack = 2
+queen = 4
+king = 5
+ace = 3
+";
+
+    #[test]
+    fn test_parse() {
+        let puzzle = Puzzle::from_toml(WALL).unwrap();
+        let game = puzzle.game();
+        assert_eq!(game.phase, Phase::Main);
+        assert_eq!(game.players[0].life, 20);
+        assert_eq!(game.players[1].life, 6);
+        assert_eq!(game.players[0].creatures.len(), 3);
+        assert_eq!(progress(&game), Progress::Playing);
+
+        assert_eq!(
+            Puzzle::from_toml("title = \"x\"\nenemy_life = 1\nhand = \"J♥ 1♥\"").unwrap_err(),
+            PuzzleError::InvalidCard("1♥".to_owned()),
+        );
+        assert_eq!(
+            Puzzle::from_toml("title = \"x\"\nenemy_life = 1\ncreatures = [\" \"]").unwrap_err(),
+            PuzzleError::EmptyCreature,
+        );
+        assert!(matches!(Puzzle::from_toml("title = \"x\""), Err(PuzzleError::Syntax(_))));
+        let no_strengths = WALL.split("[rules.strengths]").next().unwrap();
+        assert_eq!(Puzzle::from_toml(no_strengths).unwrap_err(), PuzzleError::NoStrengths);
+    }
+
+    #[test]
+    fn test_solve() {
+        let game = Puzzle::from_toml(WALL).unwrap().game();
+        assert!(can_win(&game));
+        let line = solution(&game).unwrap();
+        let ours: Vec<Action> = line.into_iter()
+            .filter(|&(player, _)| player == 0)
+            .map(|(_, action)| action)
+            .collect();
+        assert_eq!(check(&game, &ours), Ok(true));
+
+        // Holding back the Jack doesn't win
+        let attack_all = [
+            Action::Pass,
+            Action::Attack { creature: 0 },
+            Action::Attack { creature: 1 },
+            Action::Attack { creature: 2 },
+            Action::Pass,
+            Action::Pass,
+        ];
+        assert_eq!(check(&game, &attack_all), Ok(true));
+        let hold_back = [
+            Action::Pass,
+            Action::Attack { creature: 1 },
+            Action::Attack { creature: 2 },
+            Action::Pass,
+            Action::Pass,
+        ];
+        assert_eq!(check(&game, &hold_back), Ok(false));
+        assert!(check(&game, &[Action::Attack { creature: 0 }]).is_err());
+
+        // One more life is too many
+        let game = Puzzle::from_toml(&WALL.replace("= 6", "= 7")).unwrap().game();
+        assert!(!can_win(&game));
+        assert_eq!(solution(&game), None);
+    }
+
+    #[test]
+    fn test_puzzles() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/puzzles");
+        let mut count = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let puzzle = Puzzle::from_toml(&text)
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert!(solution(&puzzle.game()).is_some(), "{} has no solution", path.display());
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Player ratings and match history
+//!
+//! The registry records the result of every finished game between named
+//! players, humans or bots, and keeps an Elo rating for each of them. It is
+//! saved as JSON.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::game::Outcome;
+
+/// Rating of a player who didn't play yet
+pub const STARTING_RATING: f64 = 1500.0;
+
+/// Largest change of rating after a game
+const K_FACTOR: f64 = 32.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub rating: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Default for Record {
+    fn default() -> Record {
+        Record { rating: STARTING_RATING, wins: 0, losses: 0, draws: 0 }
+    }
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+}
+
+/// A finished game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameResult {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    /// Player 1 and player 2
+    pub players: [String; 2],
+    pub outcome: Outcome,
+    /// Change of each player's rating
+    pub changes: [f64; 2],
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Registry {
+    pub players: BTreeMap<String, Record>,
+    /// Oldest first
+    pub games: Vec<GameResult>,
+}
+
+/// The score a player is expected to get against an opponent, from 0 to 1
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+impl Registry {
+    pub fn from_json(text: &str) -> Result<Registry, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// The player's record, the default one if they didn't play yet
+    pub fn record(&self, name: &str) -> Record {
+        self.players.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Record the result of a game, updating the ratings
+    ///
+    /// Panics if both players have the same name.
+    pub fn add_game(&mut self, players: [&str; 2], outcome: Outcome, time: u64) -> &GameResult {
+        assert_ne!(players[0], players[1], "a player can't play against themselves");
+        let ratings = players.map(|name| self.record(name).rating);
+        let scores = match outcome {
+            Outcome::Win(0) => [1.0, 0.0],
+            Outcome::Win(_) => [0.0, 1.0],
+            Outcome::Draw => [0.5, 0.5],
+        };
+        let mut changes = [0.0; 2];
+        for i in 0..2 {
+            let expected = expected_score(ratings[i], ratings[1 - i]);
+            changes[i] = K_FACTOR * (scores[i] - expected);
+            let record = self.players.entry(players[i].to_owned()).or_default();
+            record.rating += changes[i];
+            match outcome {
+                Outcome::Draw => record.draws += 1,
+                Outcome::Win(winner) if winner as usize == i => record.wins += 1,
+                Outcome::Win(_) => record.losses += 1,
+            }
+        }
+        self.games.push(GameResult {
+            time,
+            players: players.map(str::to_owned),
+            outcome,
+            changes,
+        });
+        self.games.last().unwrap()
+    }
+
+    /// Players from the highest rating
+    pub fn leaderboard(&self) -> Vec<(&str, &Record)> {
+        let mut players: Vec<(&str, &Record)> = self.players.iter()
+            .map(|(name, record)| (name.as_str(), record))
+            .collect();
+        players.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+        players
+    }
+
+    /// The games a player took part in, oldest first
+    pub fn history<'a>(&'a self, name: &'a str) -> impl Iterator<Item=&'a GameResult> + 'a {
+        self.games.iter().filter(move |g| g.players.iter().any(|p| p == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Outcome;
+
+    use super::{Registry, STARTING_RATING, expected_score};
+
+    #[test]
+    fn test_ratings() {
+        assert_eq!(expected_score(1500.0, 1500.0), 0.5);
+        assert!((expected_score(1900.0, 1500.0) - 0.909).abs() < 0.001);
+
+        let mut registry = Registry::default();
+        let game = registry.add_game(["alice", "bob"], Outcome::Win(0), 10);
+        assert_eq!(game.changes, [16.0, -16.0]);
+        assert_eq!(registry.record("alice").rating, STARTING_RATING + 16.0);
+        assert_eq!(registry.record("bob").losses, 1);
+        assert_eq!(registry.record("carol").rating, STARTING_RATING);
+
+        // Beating a weaker player gives fewer points
+        let game = registry.add_game(["bob", "alice"], Outcome::Win(1), 20);
+        assert!(game.changes[1] < 16.0 && game.changes[1] > 0.0);
+        assert_eq!(game.changes[0], -game.changes[1]);
+
+        // A draw against a stronger player gives points
+        let game = registry.add_game(["carol", "alice"], Outcome::Draw, 30);
+        assert!(game.changes[0] > 0.0);
+
+        let leaderboard: Vec<&str> = registry.leaderboard().iter().map(|&(name, _)| name).collect();
+        assert_eq!(leaderboard, ["alice", "carol", "bob"]);
+        let alice = registry.record("alice");
+        assert_eq!((alice.wins, alice.losses, alice.draws, alice.games()), (2, 0, 1, 3));
+        assert_eq!(registry.history("bob").count(), 2);
+
+        // Saved and loaded again
+        assert_eq!(Registry::from_json(&registry.to_json()).unwrap(), registry);
+    }
+}
@@ -0,0 +1,221 @@
+//! Recorded games, which can be played again action by action
+//!
+//! A replay holds the cards each player started with, once the starting
+//! hands were picked, and every action that was accepted. It is saved as
+//! JSON.
+//!
+//! `Replay::trace()` renders a replay as a PlantUML sequence diagram, the
+//! same tool used for the charts in `formal-rules/`: the phases the game
+//! goes through, who has priority, and what each player does.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, Card, Event, Game, Phase, Player, RuleError};
+use crate::rules::RuleSet;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartingCards {
+    pub library: Vec<Card>,
+    pub hand: Vec<Card>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub rules: RuleSet,
+    pub players: [StartingCards; 2],
+    /// The player and the action, for each action that was accepted
+    pub actions: Vec<(u32, Action)>,
+}
+
+/// An action taken during a replay, and what it did
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub player: u32,
+    pub action: Action,
+    /// The game before the action
+    pub before: Game,
+    pub events: Vec<Event>,
+}
+
+impl Replay {
+    /// Start recording a game, which must not have started yet
+    pub fn new(game: &Game) -> Replay {
+        Replay {
+            rules: game.rules.clone(),
+            players: game.players.each_ref().map(|p| StartingCards {
+                library: p.library.clone(),
+                hand: p.hand.clone(),
+            }),
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, player: u32, action: Action) {
+        self.actions.push((player, action));
+    }
+
+    pub fn from_json(text: &str) -> Result<Replay, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Play the game again
+    ///
+    /// Returns the events of the start of the game, and each step. Fails if
+    /// an action is refused, which means the replay was not recorded with
+    /// this version of the rules.
+    pub fn play(&self) -> Result<(Vec<Event>, Vec<Step>), RuleError> {
+        let players = self.players.each_ref()
+            .map(|p| Player::new(p.library.clone(), p.hand.clone()));
+        let mut game = Game::with_rules(players, self.rules.clone());
+        let start = game.start();
+        let mut steps = Vec::with_capacity(self.actions.len());
+        for (player, action) in &self.actions {
+            let before = game.clone();
+            let events = game.apply(*player, action.clone())?;
+            steps.push(Step { player: *player, action: action.clone(), before, events });
+        }
+        Ok((start, steps))
+    }
+
+    /// Render the game as a PlantUML sequence diagram
+    pub fn trace(&self) -> Result<String, RuleError> {
+        let (start, steps) = self.play()?;
+        let mut lines = vec![
+            "@startuml".to_owned(),
+            "participant \"Player 1\" as P1".to_owned(),
+            "participant Game".to_owned(),
+            "participant \"Player 2\" as P2".to_owned(),
+        ];
+        let mut turn = 0;
+        lines.extend(start.iter().map(|e| trace_event(e, &mut turn)));
+        let mut priority = None;
+        for step in &steps {
+            if priority != Some(step.player) {
+                lines.push(format!("Game -> P{} : priority", step.player + 1));
+                priority = Some(step.player);
+            }
+            lines.push(format!("P{} -> Game : {}", step.player + 1, describe_action(step)));
+            lines.extend(step.events.iter().map(|e| trace_event(e, &mut turn)));
+        }
+        lines.push("@enduml".to_owned());
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+fn describe_action(step: &Step) -> String {
+    let us = &step.before.players[step.player as usize];
+    let enemy = &step.before.players[1 - step.player as usize];
+    match step.action {
+        Action::PlayGem { card } => format!("play gem {}", us.hand[card]),
+        Action::PlayCreature { card, ref gems } => {
+            let mut text = format!("summon {} with", us.hand[card]);
+            for &gem in gems {
+                text.push_str(&format!(" {}", us.gems[gem].card));
+            }
+            text
+        }
+        Action::Attack { creature } => format!("attack with {}", us.creatures[creature].cards[0]),
+        Action::Block { creature, attacker } => format!(
+            "block {} with {}",
+            enemy.creatures[attacker].cards[0],
+            us.creatures[creature].cards[0],
+        ),
+        Action::Pass => "pass".to_owned(),
+        Action::Undo => "undo".to_owned(),
+    }
+}
+
+/// An event as lines of the sequence diagram, counting the turns
+fn trace_event(event: &Event, turn: &mut u32) -> String {
+    match *event {
+        Event::TurnStarted { player } => {
+            *turn += 1;
+            format!(
+                "== Turn {}: Player {} ==\nhnote over Game : {:?}",
+                turn, player + 1, Phase::Main,
+            )
+        }
+        Event::CardDrawn { player, card: Some(ref card) } => {
+            format!("Game --> P{} : draws {}", player + 1, card)
+        }
+        Event::CardDrawn { player, card: None } => format!("Game --> P{} : draws a card", player + 1),
+        Event::LibraryEmpty { player } => format!("Game --> P{} : library is empty", player + 1),
+        Event::GemPlayed { player, ref card } => format!("Game --> P{} : gem {}", player + 1, card),
+        Event::CreaturePlayed { player, ref card, royal_charge } => format!(
+            "Game --> P{} : creature {}{}",
+            player + 1,
+            card,
+            if royal_charge { ", Royal Charge" } else { "" },
+        ),
+        Event::PhaseChanged { phase } => format!("hnote over Game : {:?}", phase),
+        Event::AttackerDeclared { player, creature } => {
+            format!("Game --> P{} : attacker {}", player + 1, creature + 1)
+        }
+        Event::BlockerDeclared { player, creature, attacker } => {
+            format!("Game --> P{} : creature {} blocks {}", player + 1, creature + 1, attacker + 1)
+        }
+        Event::PlayerDamaged { player, damage, life } => {
+            format!("Game --> P{} : takes {} damage, life {}", player + 1, damage, life)
+        }
+        Event::CreatureDestroyed { player, ref cards } => {
+            format!("Game --> P{} : loses creature {}", player + 1, cards[0])
+        }
+        Event::Undone { player } => format!("Game --> P{} : action taken back", player + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::game::{Action, Game, Phase, Player, deal};
+
+    use super::Replay;
+
+    #[test]
+    fn test_trace() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (library0, hand0) = deal(0, &mut rng);
+        let (library1, hand1) = deal(1, &mut rng);
+        let mut game = Game::new([Player::new(library0, hand0), Player::new(library1, hand1)]);
+        let mut replay = Replay::new(&game);
+        game.start();
+        let gem = game.players[0].hand.iter().position(|c| c.face.is_gem()).unwrap();
+        for action in [Action::PlayGem { card: gem }, Action::Pass, Action::Pass, Action::Pass] {
+            game.apply(0, action.clone()).unwrap();
+            replay.record(0, action);
+        }
+        replay.record(1, Action::Pass);
+        game.apply(1, Action::Pass).unwrap();
+
+        // Saved and loaded again
+        let replay = Replay::from_json(&replay.to_json()).unwrap();
+        let (_, steps) = replay.play().unwrap();
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[4].before.phase, Phase::FinalInstant);
+
+        let trace = replay.trace().unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines[0], "@startuml");
+        assert_eq!(lines[4], "== Turn 1: Player 1 ==");
+        assert_eq!(lines[5], "hnote over Game : Main");
+        assert!(lines[6].starts_with("Game --> P1 : draws "));
+        assert_eq!(lines[7], "Game -> P1 : priority");
+        assert!(lines[8].starts_with("P1 -> Game : play gem "));
+        assert!(lines[9].starts_with("Game --> P1 : gem "));
+        assert_eq!(lines[10], "P1 -> Game : pass");
+        assert_eq!(lines[11], "hnote over Game : Attack");
+        assert!(trace.contains("Game -> P2 : priority\nP2 -> Game : pass\n== Turn 2: Player 2 =="));
+        assert_eq!(lines.last(), Some(&"@enduml"));
+
+        // Replays that don't follow the rules are refused
+        let mut replay = replay;
+        replay.record(0, Action::Pass);
+        assert!(replay.play().is_err());
+    }
+}
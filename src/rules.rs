@@ -0,0 +1,198 @@
+//! Rule variants
+//!
+//! All the numbers the game uses live in a `RuleSet`, so house rules can be
+//! tried without changing the code. It can be read from TOML or JSON, and
+//! missing fields keep their default value:
+//!
+//! ```toml
+//! starting_life = 30
+//! mulligans = 2
+//! decking = "lose"
+//!
+//! [costs]
+//! king = 6
+//!
+//! [strengths]
+//! jack = 2
+//! queen = 4
+//! king = 5
+//! ace = 3
+//!
+//! [payment]
+//! keep_straight = false
+//!
+//! [deck]
+//! size = 30
+//! suits = ["Hearts", "Diamonds"]
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::deck::DeckRules;
+use crate::game::Face;
+use crate::pay::Preferences;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    pub starting_life: i32,
+    /// Number of cards in the starting hand
+    pub hand_size: usize,
+    /// Number of gems to tap to summon each face as a creature
+    pub costs: Costs,
+    /// Combat strength of each face, summed over the cards of a creature
+    ///
+    /// The rules don't give these numbers yet (see `formal-rules/README.md`),
+    /// so combat deals no damage unless a house rule sets them.
+    pub strengths: Option<Strengths>,
+    pub gems_per_turn: u32,
+    /// Number of times a player can replace their starting hand
+    pub mulligans: u32,
+    /// What happens when a player has to draw from an empty library
+    pub decking: Decking,
+    /// Whether players can take back their actions, for casual games
+    pub undo: bool,
+    /// How the interfaces and bots pick the gems to pay with, not a rule but
+    /// kept here so they all agree
+    pub payment: Preferences,
+    /// What constructed decks have to follow
+    pub deck: DeckRules,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Costs {
+    pub jack: u32,
+    pub queen: u32,
+    pub king: u32,
+    pub ace: u32,
+}
+
+/// Combat strengths, which have to be given for every face
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Strengths {
+    pub jack: i32,
+    pub queen: i32,
+    pub king: i32,
+    pub ace: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decking {
+    /// The player doesn't draw, and keeps playing
+    Continue,
+    /// The player loses the game
+    Lose,
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet {
+            starting_life: 20,
+            hand_size: 5,
+            costs: Costs::default(),
+            strengths: None,
+            gems_per_turn: 1,
+            mulligans: 1,
+            decking: Decking::Continue,
+            undo: false,
+            payment: Preferences::default(),
+            deck: DeckRules::default(),
+        }
+    }
+}
+
+impl Default for Costs {
+    fn default() -> Costs {
+        Costs {
+            jack: 2,
+            queen: 4,
+            king: 5,
+            ace: 3,
+        }
+    }
+}
+
+impl RuleSet {
+    pub fn from_toml(text: &str) -> Result<RuleSet, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn from_json(text: &str) -> Result<RuleSet, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    /// Number of gems to tap to summon this card as a creature, or `None` if
+    /// it is a gem
+    pub fn cost(&self, face: Face) -> Option<u32> {
+        match face {
+            Face::Jack => Some(self.costs.jack),
+            Face::Queen => Some(self.costs.queen),
+            Face::King => Some(self.costs.king),
+            Face::Ace => Some(self.costs.ace),
+            _ => None,
+        }
+    }
+
+    /// Combat strength this card adds to a creature, or `None` if combat
+    /// deals no damage with these rules
+    pub fn strength(&self, face: Face) -> Option<i32> {
+        let strengths = self.strengths.as_ref()?;
+        Some(match face {
+            Face::Jack => strengths.jack,
+            Face::Queen => strengths.queen,
+            Face::King => strengths.king,
+            Face::Ace => strengths.ace,
+            _ => 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Face, Suit};
+
+    use super::{Decking, RuleSet};
+
+    #[test]
+    fn test_load() {
+        let rules = RuleSet::from_toml("").unwrap();
+        assert_eq!(rules, RuleSet::default());
+        assert_eq!(rules.cost(Face::Queen), Some(4));
+        assert_eq!(rules.cost(Face::Seven), None);
+
+        let rules = RuleSet::from_toml(
+            "starting_life = 30\ndecking = \"lose\"\n\n[costs]\nking = 6\n",
+        ).unwrap();
+        assert_eq!(rules.starting_life, 30);
+        assert_eq!(rules.decking, Decking::Lose);
+        assert_eq!(rules.cost(Face::King), Some(6));
+        assert_eq!(rules.cost(Face::Jack), Some(2));
+        assert_eq!(rules.hand_size, 5);
+        assert_eq!(rules.strength(Face::King), None);
+
+        // Strengths are a house rule, given for every face
+        let rules = RuleSet::from_toml(
+            "[strengths]\njack = 1\nqueen = 2\nking = 3\nace = 4\n",
+        ).unwrap();
+        assert_eq!(rules.strength(Face::King), Some(3));
+        assert_eq!(rules.strength(Face::Two), Some(0));
+        assert!(RuleSet::from_toml("[strengths]\njack = 1\n").is_err());
+
+        let rules = RuleSet::from_json(r#"{"gems_per_turn": 2, "mulligans": 0}"#).unwrap();
+        assert_eq!(rules.gems_per_turn, 2);
+        assert_eq!(rules.mulligans, 0);
+
+        let rules = RuleSet::from_toml("[payment]\nkeep_high = false\n").unwrap();
+        assert!(!rules.payment.keep_high && rules.payment.royal_charge);
+
+        let rules = RuleSet::from_toml("[deck]\nsize = 30\nsuits = [\"Hearts\"]\n").unwrap();
+        assert_eq!((rules.deck.size, rules.deck.max_copies), (30, 2));
+        assert_eq!(rules.deck.suits, [Suit::Hearts]);
+
+        // Typos are not silently ignored
+        assert!(RuleSet::from_toml("starting_lives = 30").is_err());
+    }
+}
@@ -0,0 +1,467 @@
+//! Setting up a game in a given position, to test the rules
+//!
+//! A scenario starts from an empty board, in the main phase of player 1's
+//! turn, and is built up card by card. Cards are written like in deck lists:
+//!
+//! ```
+//! use superjack::game::{CreatureStatus, Phase};
+//! use superjack::scenario::Scenario;
+//!
+//! let game = Scenario::new()
+//!     .turn(0, Phase::Attack)
+//!     .gems(0, "2♥ 3♥")
+//!     .tapped_gems(0, "4S")
+//!     .creature(0, "Q♥", CreatureStatus::Ready)
+//!     .equipped("5♥ 5♦")
+//!     .life(1, 4)
+//!     .build();
+//! assert_eq!(game.players[0].gems.len(), 3);
+//! ```
+//!
+//! Invalid cards and inconsistent positions make the builder panic, it is
+//! meant for tests and positions written in the code.
+
+use crate::deck::parse_card;
+use crate::game::{Card, Creature, CreatureStatus, Game, Gem, Phase, Player, sort_hand};
+use crate::rules::RuleSet;
+
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    rules: RuleSet,
+    current_player: u32,
+    phase: Phase,
+    players: [Player; 2],
+    /// Creatures in the order they were added, and their part in combat
+    creatures: Vec<(u32, Creature, Attack)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Attack {
+    None,
+    Attacking,
+    /// Blocking the attacker with this top card
+    Blocking(Card),
+}
+
+fn parse_cards(player: u32, cards: &str) -> Vec<Card> {
+    cards.split_whitespace()
+        .map(|text| match parse_card(text) {
+            Some((face, suit)) => Card { player, suit, face },
+            None => panic!("Invalid card {:?}", text),
+        })
+        .collect()
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario::new()
+    }
+}
+
+impl Scenario {
+    /// An empty board, with the default rules
+    pub fn new() -> Scenario {
+        Scenario {
+            rules: RuleSet::default(),
+            current_player: 0,
+            phase: Phase::Main,
+            players: [
+                Player::new(Vec::new(), Vec::new()),
+                Player::new(Vec::new(), Vec::new()),
+            ],
+            creatures: Vec::new(),
+        }
+    }
+
+    /// Use other rules, this resets the life of both players
+    pub fn rules(mut self, rules: RuleSet) -> Scenario {
+        for player in &mut self.players {
+            player.life = rules.starting_life;
+        }
+        self.rules = rules;
+        self
+    }
+
+    pub fn turn(mut self, player: u32, phase: Phase) -> Scenario {
+        self.current_player = player;
+        self.phase = phase;
+        self
+    }
+
+    pub fn life(mut self, player: u32, life: i32) -> Scenario {
+        self.players[player as usize].life = life;
+        self
+    }
+
+    pub fn hand(mut self, player: u32, cards: &str) -> Scenario {
+        self.players[player as usize].hand.extend(parse_cards(player, cards));
+        self
+    }
+
+    /// Cards in the library, the first one being drawn first
+    pub fn library(mut self, player: u32, cards: &str) -> Scenario {
+        let library = &mut self.players[player as usize].library;
+        library.splice(0..0, parse_cards(player, cards).into_iter().rev());
+        self
+    }
+
+    pub fn graveyard(mut self, player: u32, cards: &str) -> Scenario {
+        self.players[player as usize].graveyard.extend(parse_cards(player, cards));
+        self
+    }
+
+    pub fn gems(self, player: u32, cards: &str) -> Scenario {
+        self.add_gems(player, cards, false)
+    }
+
+    pub fn tapped_gems(self, player: u32, cards: &str) -> Scenario {
+        self.add_gems(player, cards, true)
+    }
+
+    fn add_gems(mut self, player: u32, cards: &str, tapped: bool) -> Scenario {
+        let gems = parse_cards(player, cards).into_iter().map(|card| Gem { card, tapped });
+        self.players[player as usize].gems.extend(gems);
+        self
+    }
+
+    /// Number of gems the player already played this turn
+    pub fn gems_played(mut self, player: u32, count: u32) -> Scenario {
+        self.players[player as usize].gems_played = count;
+        self
+    }
+
+    /// Add a creature made of one card, or of several for stacked creatures
+    pub fn creature(mut self, player: u32, cards: &str, status: CreatureStatus) -> Scenario {
+        let cards = parse_cards(player, cards);
+        assert!(!cards.is_empty(), "Creature has no card");
+        let creature = Creature {
+            cards,
+            royal_charge: false,
+            status,
+            equipment: Vec::new(),
+        };
+        self.creatures.push((player, creature, Attack::None));
+        self
+    }
+
+    fn last_creature(&mut self) -> &mut (u32, Creature, Attack) {
+        self.creatures.last_mut().expect("No creature was added")
+    }
+
+    /// Give Royal Charge to the last creature
+    pub fn royal_charge(mut self) -> Scenario {
+        self.last_creature().1.royal_charge = true;
+        self
+    }
+
+    /// Equip the last creature with pairs of cards
+    pub fn equipped(mut self, cards: &str) -> Scenario {
+        let (player, creature, _) = self.last_creature();
+        let cards = parse_cards(*player, cards);
+        assert!(cards.len().is_multiple_of(2), "Equipment comes in pairs of cards");
+        creature.equipment.extend(cards.chunks(2).map(|pair| [pair[0].clone(), pair[1].clone()]));
+        self
+    }
+
+    /// Have the last creature attack, which taps it
+    pub fn attacking(mut self) -> Scenario {
+        let (_, creature, attack) = self.last_creature();
+        creature.status = CreatureStatus::Tapped;
+        *attack = Attack::Attacking;
+        self
+    }
+
+    /// Have the last creature block the attacker with the given top card
+    pub fn blocking(mut self, attacker: &str) -> Scenario {
+        let (player, _, attack) = self.last_creature();
+        let mut cards = parse_cards(1 - *player, attacker);
+        assert!(cards.len() == 1, "Attackers are given by their top card");
+        *attack = Attack::Blocking(cards.remove(0));
+        self
+    }
+
+    pub fn build(self) -> Game {
+        // with_rules() resets the life
+        let life = self.players.each_ref().map(|p| p.life);
+        let mut game = Game::with_rules(self.players, self.rules);
+        for (player, life) in game.players.iter_mut().zip(life) {
+            player.life = life;
+        }
+        game.current_player = self.current_player;
+        game.phase = self.phase;
+
+        for player in &mut game.players {
+            sort_hand(&mut player.hand);
+            player.gems.sort_by_key(|g| (g.card.color(), g.card.face, g.card.suit));
+        }
+        for (player, creature, _) in &self.creatures {
+            game.players[*player as usize].add_creature(creature.clone());
+        }
+
+        // Combat refers to the creatures by their position, known once they
+        // are all sorted
+        let position = |game: &Game, player: u32, top: &Card| {
+            game.players[player as usize].creatures.iter()
+                .position(|c| c.cards[0] == *top)
+        };
+        let attacking = self.current_player;
+        for (player, creature, attack) in &self.creatures {
+            if *attack == Attack::Attacking {
+                assert_eq!(*player, attacking, "Only the current player attacks");
+                game.attackers.push(position(&game, *player, &creature.cards[0]).unwrap());
+            }
+        }
+        for (player, creature, attack) in &self.creatures {
+            if let Attack::Blocking(attacker) = attack {
+                assert_eq!(*player, 1 - attacking, "Only the enemy blocks");
+                let blocker = position(&game, *player, &creature.cards[0]).unwrap();
+                let attacker = position(&game, attacking, attacker)
+                    .filter(|a| game.attackers.contains(a))
+                    .unwrap_or_else(|| panic!("{} is not attacking", attacker));
+                game.blockers.push((blocker, attacker));
+            }
+        }
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Action, CreatureStatus, Event, Outcome, Phase, RuleError};
+    use crate::rules::{Decking, RuleSet, Strengths};
+
+    use super::Scenario;
+
+    #[test]
+    fn test_build() {
+        let game = Scenario::new()
+            .turn(1, Phase::Defend)
+            .hand(0, "K♠ 2♥ J♦")
+            .library(0, "3♣ 4♣")
+            .gems(1, "5♦ 2♠")
+            .tapped_gems(1, "3♥")
+            .creature(1, "K♥", CreatureStatus::Ready).attacking()
+            .creature(1, "J♥ J♦", CreatureStatus::Ready).attacking()
+            .creature(0, "Q♠", CreatureStatus::Ready).blocking("J♥")
+            .build();
+        let hand: Vec<String> = game.players[0].hand.iter().map(|c| c.to_string()).collect();
+        assert_eq!(hand, ["2♥", "J♦", "K♠"]);
+        assert_eq!(game.players[0].library.last().unwrap().to_string(), "3♣");
+        let gems: Vec<(String, bool)> = game.players[1].gems.iter()
+            .map(|g| (g.card.to_string(), g.tapped))
+            .collect();
+        assert_eq!(gems, [("3♥".to_owned(), true), ("5♦".to_owned(), false), ("2♠".to_owned(), false)]);
+        assert_eq!(game.players[1].creatures[0].cards.len(), 2);
+        assert_eq!(game.players[1].creatures[1].status, CreatureStatus::Tapped);
+        assert_eq!(game.attackers, [1, 0]);
+        assert_eq!(game.blockers, [(0, 0)]);
+        assert_eq!(game.priority(), 0);
+    }
+
+    #[test]
+    fn test_royal_charge() {
+        let game = Scenario::new()
+            .hand(0, "J♥")
+            .gems(0, "2♥ 3♥ 5♦ 3♠")
+            .build();
+
+        // A straight of the exact cost
+        let mut charged = game.clone();
+        let events = charged.apply(0, Action::PlayCreature { card: 0, gems: vec![0, 1] }).unwrap();
+        assert!(matches!(events[..], [Event::CreaturePlayed { royal_charge: true, .. }]));
+        assert!(charged.players[0].creatures[0].royal_charge);
+
+        // Not a straight
+        let mut game = game;
+        let events = game.apply(0, Action::PlayCreature { card: 0, gems: vec![0, 2] }).unwrap();
+        assert!(matches!(events[..], [Event::CreaturePlayed { royal_charge: false, .. }]));
+        assert_eq!(game.players[0].creatures[0].status, CreatureStatus::Untrained);
+    }
+
+    #[test]
+    fn test_payment_errors() {
+        let mut game = Scenario::new()
+            .hand(0, "J♥ Q♥")
+            .gems(0, "2♥ 3♠")
+            .tapped_gems(0, "4♥")
+            .build();
+        // Gems are sorted red first: 2♥ 4♥ 3♠
+        assert_eq!(
+            game.apply(0, Action::PlayCreature { card: 0, gems: vec![0, 1] }),
+            Err(RuleError::GemUnavailable),
+        );
+        assert_eq!(
+            game.apply(0, Action::PlayCreature { card: 0, gems: vec![0, 2] }),
+            Err(RuleError::GemUnavailable),
+        );
+        assert_eq!(
+            game.apply(0, Action::PlayCreature { card: 1, gems: vec![0] }),
+            Err(RuleError::WrongGemCount { cost: 4 }),
+        );
+        assert_eq!(game.legal_actions(0), [Action::Pass]);
+    }
+
+    #[test]
+    fn test_gem_limit() {
+        let mut game = Scenario::new()
+            .hand(0, "2♥ 3♥")
+            .gems_played(0, 1)
+            .build();
+        assert_eq!(game.apply(0, Action::PlayGem { card: 0 }), Err(RuleError::TooManyGems { limit: 1 }));
+
+        let mut game = Scenario::new()
+            .rules(RuleSet { gems_per_turn: 2, ..RuleSet::default() })
+            .hand(0, "2♥ 3♥")
+            .gems_played(0, 1)
+            .build();
+        game.apply(0, Action::PlayGem { card: 0 }).unwrap();
+        assert_eq!(game.apply(0, Action::PlayGem { card: 0 }), Err(RuleError::TooManyGems { limit: 2 }));
+    }
+
+    #[test]
+    fn test_attack_readiness() {
+        let mut game = Scenario::new()
+            .turn(0, Phase::Attack)
+            .creature(0, "J♥", CreatureStatus::Untrained)
+            .creature(0, "Q♠", CreatureStatus::Ready)
+            .build();
+        assert_eq!(game.apply(0, Action::Attack { creature: 0 }), Err(RuleError::CreatureNotReady));
+        game.apply(0, Action::Attack { creature: 1 }).unwrap();
+        assert_eq!(game.apply(0, Action::Attack { creature: 1 }), Err(RuleError::CreatureNotReady));
+
+        // Tapped creatures can't block
+        let mut game = Scenario::new()
+            .turn(0, Phase::Defend)
+            .creature(0, "K♥", CreatureStatus::Ready).attacking()
+            .creature(1, "J♠", CreatureStatus::Tapped)
+            .creature(1, "J♣", CreatureStatus::Untrained)
+            .build();
+        assert_eq!(game.apply(1, Action::Block { creature: 0, attacker: 0 }), Err(RuleError::CreatureNotReady));
+        game.apply(1, Action::Block { creature: 1, attacker: 0 }).unwrap();
+    }
+
+    #[test]
+    fn test_combat() {
+        // The rules don't say how much damage creatures deal yet, so without
+        // a house rule combat only ends
+        let mut game = Scenario::new()
+            .turn(0, Phase::DefendInstant)
+            .life(1, 2)
+            .creature(0, "K♥", CreatureStatus::Ready).attacking()
+            .creature(0, "J♥", CreatureStatus::Ready).attacking().equipped("2♥ 3♥")
+            .creature(1, "J♠", CreatureStatus::Ready).blocking("J♥")
+            .build();
+        let events = game.apply(1, Action::Pass).unwrap();
+        assert_eq!(events, [Event::PhaseChanged { phase: Phase::SecondMain }]);
+        assert!(game.attackers.is_empty() && game.blockers.is_empty());
+        assert_eq!(game.players[1].life, 2);
+        assert_eq!(game.players[0].creatures.len(), 2);
+        assert_eq!(game.players[1].creatures.len(), 1);
+        assert_eq!(game.outcome(), None);
+    }
+
+    /// Strengths equal to the costs, as a house rule
+    fn strengths() -> RuleSet {
+        RuleSet {
+            strengths: Some(Strengths { jack: 2, queen: 4, king: 5, ace: 3 }),
+            ..RuleSet::default()
+        }
+    }
+
+    #[test]
+    fn test_house_combat() {
+        // K (5) is unblocked, Q (4) beats J (2), J (2) trades with J (2)
+        let mut game = Scenario::new()
+            .rules(strengths())
+            .turn(0, Phase::DefendInstant)
+            .creature(0, "K♥", CreatureStatus::Ready).attacking()
+            .creature(0, "Q♥", CreatureStatus::Ready).attacking()
+            .creature(0, "J♥", CreatureStatus::Ready).attacking().equipped("2♥ 3♥")
+            .creature(1, "J♠", CreatureStatus::Ready).blocking("Q♥")
+            .creature(1, "J♣", CreatureStatus::Ready).blocking("J♥")
+            .build();
+        let events = game.apply(1, Action::Pass).unwrap();
+        assert!(events.contains(&Event::PlayerDamaged { player: 1, damage: 5, life: 15 }));
+        let destroyed = events.iter()
+            .filter(|e| matches!(e, Event::CreatureDestroyed { .. }))
+            .count();
+        assert_eq!(destroyed, 3);
+        assert_eq!(game.phase, Phase::SecondMain);
+        assert!(game.attackers.is_empty() && game.blockers.is_empty());
+
+        let creatures: Vec<String> = game.players[0].creatures.iter()
+            .map(|c| c.cards[0].to_string())
+            .collect();
+        assert_eq!(creatures, ["Q♥", "K♥"]);
+        assert!(game.players[1].creatures.is_empty());
+        // The equipment goes to the graveyard with the creature
+        assert_eq!(game.players[0].graveyard.len(), 3);
+        assert_eq!(game.players[1].graveyard.len(), 2);
+    }
+
+    #[test]
+    fn test_stacked_strength() {
+        // J J (4) against Q (4) destroys both
+        let mut game = Scenario::new()
+            .rules(strengths())
+            .turn(0, Phase::DefendInstant)
+            .creature(0, "J♥ J♦", CreatureStatus::Ready).attacking()
+            .creature(1, "Q♠", CreatureStatus::Ready).blocking("J♥")
+            .build();
+        game.apply(1, Action::Pass).unwrap();
+        assert!(game.players[0].creatures.is_empty());
+        assert!(game.players[1].creatures.is_empty());
+        assert_eq!(game.players[0].graveyard.len(), 2);
+    }
+
+    #[test]
+    fn test_lethal() {
+        let mut game = Scenario::new()
+            .rules(strengths())
+            .turn(0, Phase::DefendInstant)
+            .life(1, 2)
+            .creature(0, "J♥", CreatureStatus::Ready).attacking()
+            .build();
+        let events = game.apply(1, Action::Pass).unwrap();
+        assert!(events.contains(&Event::PlayerDamaged { player: 1, damage: 2, life: 0 }));
+        assert_eq!(game.outcome(), Some(Outcome::Win(0)));
+        assert_eq!(game.apply(0, Action::Pass), Err(RuleError::GameOver));
+        assert!(game.legal_actions(0).is_empty());
+    }
+
+    #[test]
+    fn test_decking() {
+        let end_of_turn = |decking| Scenario::new()
+            .rules(RuleSet { decking, ..RuleSet::default() })
+            .turn(0, Phase::FinalInstant)
+            .library(0, "2♥")
+            .build();
+
+        let mut game = end_of_turn(Decking::Lose);
+        let events = game.apply(1, Action::Pass).unwrap();
+        assert_eq!(events, [Event::TurnStarted { player: 1 }, Event::LibraryEmpty { player: 1 }]);
+        assert_eq!(game.outcome(), Some(Outcome::Win(0)));
+
+        let mut game = end_of_turn(Decking::Continue);
+        game.apply(1, Action::Pass).unwrap();
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.phase, Phase::Main);
+    }
+
+    #[test]
+    fn test_undo_summon() {
+        let mut game = Scenario::new()
+            .rules(RuleSet { undo: true, ..RuleSet::default() })
+            .hand(0, "J♠ 4♠")
+            .gems(0, "2♠ 3♠")
+            .build();
+        game.apply(0, Action::PlayCreature { card: 1, gems: vec![0, 1] }).unwrap();
+        game.apply(0, Action::PlayGem { card: 0 }).unwrap();
+        assert_eq!(game.apply(0, Action::Undo).unwrap(), [Event::Undone { player: 0 }]);
+        assert_eq!(game.apply(0, Action::Undo).unwrap(), [Event::Undone { player: 0 }]);
+        assert!(game.players[0].creatures.is_empty());
+        assert!(game.players[0].gems.iter().all(|g| !g.tapped));
+        assert_eq!(game.players[0].hand.len(), 2);
+        assert_eq!(game.apply(0, Action::Undo), Err(RuleError::NothingToUndo));
+    }
+}
@@ -0,0 +1,445 @@
+//! Tournaments between bots and humans
+//!
+//! Every pairing plays a best-of-N match, the players taking turns going
+//! first. A tournament is read from TOML, players without a strategy being
+//! humans:
+//!
+//! ```toml
+//! format = "swiss"
+//! rounds = 3
+//! best_of = 3
+//! seed = 42
+//!
+//! [rules.strengths]
+//! jack = 2
+//! queen = 4
+//! king = 5
+//! ace = 3
+//!
+//! [[players]]
+//! name = "Greedy"
+//! strategy = "greedy"
+//!
+//! [[players]]
+//! name = "Random"
+//! strategy = "random"
+//!
+//! [[players]]
+//! name = "Alice"
+//! ```
+//!
+//! Games between bots are played headless, and everything random in them
+//! comes from the seed, so running a tournament of bots again gives the same
+//! results. Games with a human are played through an interface, given to
+//! `run`.
+//!
+//! The rules don't say how much damage creatures deal yet, so the rule set
+//! has to give them strengths as a house rule. Otherwise nobody could win by
+//! playing better, only by the other player running out of cards first.
+//!
+//! A match win is worth 3 points, a draw 1. In a Swiss tournament, players
+//! play against players with the same number of points, and when there is an
+//! odd number of players, one of them gets a bye which counts as a win.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use crate::bot::{Played, Strategy, new_bot, play_game};
+use crate::deck::DeckList;
+use crate::game::Outcome;
+use crate::matches::{FirstPlayer, Match};
+use crate::rules::RuleSet;
+
+const WIN_POINTS: u32 = 3;
+const DRAW_POINTS: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Everyone plays everyone
+    RoundRobin,
+    Swiss,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Participant {
+    pub name: String,
+    /// How the bot plays, None for a human
+    #[serde(default)]
+    pub strategy: Option<Strategy>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub format: Format,
+    /// Number of rounds of a Swiss tournament, enough to find a single winner
+    /// if not given
+    pub rounds: Option<u32>,
+    /// Maximum number of games in a match
+    #[serde(default = "default_best_of")]
+    pub best_of: u32,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub rules: RuleSet,
+    pub players: Vec<Participant>,
+}
+
+fn default_best_of() -> u32 {
+    3
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|e| e.to_string())?;
+        if config.players.len() < 2 {
+            return Err("A tournament needs at least 2 players".to_owned());
+        }
+        if config.best_of == 0 {
+            return Err("Matches need at least one game".to_owned());
+        }
+        if config.format == Format::RoundRobin && config.rounds.is_some() {
+            return Err("Only Swiss tournaments have a number of rounds".to_owned());
+        }
+        if config.rules.strengths.is_none() {
+            return Err("Combat deals no damage without [rules.strengths], games couldn't be won by play".to_owned());
+        }
+        for (i, player) in config.players.iter().enumerate() {
+            if config.players[..i].iter().any(|p| p.name == player.name) {
+                return Err(format!("Two players are named {}", player.name));
+            }
+        }
+        Ok(config)
+    }
+
+    fn rounds(&self) -> u32 {
+        let count = self.players.len() as u32;
+        match self.format {
+            Format::RoundRobin => count - 1 + count % 2,
+            Format::Swiss => self.rounds.unwrap_or_else(|| count.next_power_of_two().trailing_zeros()),
+        }
+    }
+}
+
+/// A match between two players, given by their position in the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pairing {
+    pub round: u32,
+    /// The first player goes first in the first game
+    pub players: [usize; 2],
+    pub wins: [u32; 2],
+    pub draws: u32,
+    /// Number of games won by the player going first
+    pub first_player_wins: u32,
+    /// Total number of turns in the games
+    pub turns: u32,
+    /// The result of each game, `Win(0)` when the first player won
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Pairing {
+    pub fn games(&self) -> u32 {
+        self.wins[0] + self.wins[1] + self.draws
+    }
+
+    /// Position of the winner in `players`, None for a draw
+    pub fn winner(&self) -> Option<usize> {
+        match self.wins[0].cmp(&self.wins[1]) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Standing {
+    pub player: usize,
+    pub points: u32,
+    /// Matches won, lost and drawn, byes included in the wins
+    pub matches: [u32; 3],
+    /// Games won, lost and drawn
+    pub games: [u32; 3],
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Results {
+    pub pairings: Vec<Pairing>,
+    /// The round and the player, for players that didn't play in a Swiss round
+    pub byes: Vec<(u32, usize)>,
+}
+
+impl Results {
+    /// Players from the most points, then the best game record
+    pub fn standings(&self, players: usize) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = (0..players)
+            .map(|player| Standing { player, ..Standing::default() })
+            .collect();
+        for pairing in &self.pairings {
+            for side in 0..2 {
+                let standing = &mut standings[pairing.players[side]];
+                let result = match pairing.winner() {
+                    Some(winner) if winner == side => 0,
+                    Some(_) => 1,
+                    None => 2,
+                };
+                standing.matches[result] += 1;
+                standing.games[0] += pairing.wins[side];
+                standing.games[1] += pairing.wins[1 - side];
+                standing.games[2] += pairing.draws;
+            }
+        }
+        for &(_, player) in &self.byes {
+            standings[player].matches[0] += 1;
+        }
+        for standing in &mut standings {
+            standing.points = standing.matches[0] * WIN_POINTS + standing.matches[2] * DRAW_POINTS;
+        }
+        standings.sort_by_key(|s| {
+            let difference = s.games[0] as i64 - s.games[1] as i64;
+            (std::cmp::Reverse(s.points), std::cmp::Reverse(difference), s.player)
+        });
+        standings
+    }
+
+    fn have_played(&self, a: usize, b: usize) -> bool {
+        self.pairings.iter().any(|p| p.players == [a, b] || p.players == [b, a])
+    }
+}
+
+/// Play a best-of-N match, the players taking turns going first
+///
+/// Games with a human are played by `host`, given the participants in seat
+/// order.
+fn play_match<E>(
+    config: &Config,
+    round: u32,
+    players: [usize; 2],
+    seed: u64,
+    host: &mut impl FnMut(&[DeckList; 2], &RuleSet, [&Participant; 2]) -> Result<Played, E>,
+) -> Result<Pairing, E> {
+    let decks = [DeckList::standard(), DeckList::standard()];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut m = Match::new(decks, config.rules.clone(), config.best_of, FirstPlayer::Alternate);
+    let mut pairing = Pairing {
+        round,
+        players,
+        wins: [0, 0],
+        draws: 0,
+        first_player_wins: 0,
+        turns: 0,
+        outcomes: Vec::new(),
+    };
+    while m.outcome().is_none() {
+        // Position in `players` of the players in seats 1 and 2
+        let seats = m.next_game().unwrap();
+        let participants = seats.map(|seat| &config.players[players[seat as usize]]);
+        let decks = m.seated_decks().unwrap();
+        let seed = rng.gen();
+        let played = match participants.map(|p| p.strategy) {
+            [Some(first), Some(second)] => {
+                let (mut first, mut second) = (new_bot(first), new_bot(second));
+                play_game(&decks, &config.rules, [&mut *first, &mut *second], seed)
+            }
+            _ => host(&decks, &config.rules, participants)?,
+        };
+        pairing.turns += played.turns;
+        if played.outcome == Outcome::Win(0) {
+            pairing.first_player_wins += 1;
+        }
+        m.finish_game(played.outcome).unwrap();
+    }
+    pairing.wins = m.wins;
+    pairing.draws = m.draws;
+    pairing.outcomes = m.games.iter().map(|g| g.outcome).collect();
+    Ok(pairing)
+}
+
+/// The pairings of each round of a round robin, by the circle method
+fn round_robin(players: usize) -> Vec<Vec<[usize; 2]>> {
+    // None sits out the round, if there is an odd number of players
+    let mut circle: Vec<Option<usize>> = (0..players).map(Some).collect();
+    if players % 2 == 1 {
+        circle.push(None);
+    }
+    let size = circle.len();
+    let mut rounds = Vec::new();
+    for _ in 0..size - 1 {
+        let round = (0..size / 2)
+            .filter_map(|i| match (circle[i], circle[size - 1 - i]) {
+                (Some(a), Some(b)) => Some([a, b]),
+                _ => None,
+            })
+            .collect();
+        rounds.push(round);
+        circle[1..].rotate_right(1);
+    }
+    rounds
+}
+
+/// Pair players with the same number of points, avoiding rematches
+///
+/// Returns the pairings, and the player that gets a bye if any.
+fn swiss_round(results: &Results, players: usize) -> (Vec<[usize; 2]>, Option<usize>) {
+    let mut unpaired: Vec<usize> = results.standings(players).iter().map(|s| s.player).collect();
+    // The lowest ranked player that didn't get a bye yet
+    let bye = if players % 2 == 1 {
+        let position = unpaired.iter()
+            .rposition(|&b| !results.byes.iter().any(|&(_, bye)| bye == b))
+            .unwrap_or(unpaired.len() - 1);
+        Some(unpaired.remove(position))
+    } else {
+        None
+    };
+    let mut pairs = Vec::new();
+    while !unpaired.is_empty() {
+        let first = unpaired.remove(0);
+        let opponent = unpaired.iter()
+            .position(|&b| !results.have_played(first, b))
+            .unwrap_or(0);
+        pairs.push([first, unpaired.remove(opponent)]);
+    }
+    (pairs, bye)
+}
+
+/// Run the tournament, `host` playing the games with a human
+pub fn run<E>(
+    config: &Config,
+    mut host: impl FnMut(&[DeckList; 2], &RuleSet, [&Participant; 2]) -> Result<Played, E>,
+) -> Result<Results, E> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut results = Results::default();
+    let players = config.players.len();
+    let schedule = round_robin(players);
+    for round in 1..=config.rounds() {
+        let pairs = match config.format {
+            Format::RoundRobin => schedule[round as usize - 1].clone(),
+            Format::Swiss => {
+                let (pairs, bye) = swiss_round(&results, players);
+                results.byes.extend(bye.map(|player| (round, player)));
+                pairs
+            }
+        };
+        for pair in pairs {
+            let pairing = play_match(config, round, pair, rng.gen(), &mut host)?;
+            results.pairings.push(pairing);
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bot::Played;
+    use crate::deck::DeckList;
+    use crate::game::Outcome;
+    use crate::rules::RuleSet;
+
+    use super::{Config, Format, Participant, Results, round_robin, run};
+
+    /// Combat damage, as a house rule
+    const STRENGTHS: &str = "[rules.strengths]\njack = 2\nqueen = 4\nking = 5\nace = 3\n";
+
+    fn config(format: &str, bots: usize) -> Config {
+        let mut text = format!("format = \"{}\"\nseed = 7\n{}", format, STRENGTHS);
+        for i in 0..bots {
+            let strategy = if i == 0 { "greedy" } else { "random" };
+            text.push_str(&format!("[[players]]\nname = \"bot{}\"\nstrategy = \"{}\"\n", i, strategy));
+        }
+        Config::from_toml(&text).unwrap()
+    }
+
+    /// Run a tournament of bots only
+    fn run_bots(config: &Config) -> Results {
+        let no_humans = |_: &[DeckList; 2], _: &RuleSet, _: [&Participant; 2]| -> Result<Played, ()> {
+            unreachable!()
+        };
+        run(config, no_humans).unwrap()
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let rounds = round_robin(5);
+        assert_eq!(rounds.len(), 5);
+        let mut pairs: Vec<[usize; 2]> = rounds.iter().flatten()
+            .map(|&[a, b]| [a.min(b), a.max(b)])
+            .collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        assert_eq!(pairs.len(), 10);
+
+        let config = config("round_robin", 4);
+        assert_eq!(config.format, Format::RoundRobin);
+        let results = run_bots(&config);
+        assert_eq!(results.pairings.len(), 6);
+        for pairing in &results.pairings {
+            assert!(pairing.games() >= 2 && pairing.games() <= 3);
+            assert_eq!(pairing.outcomes.len() as u32, pairing.games());
+            assert!(pairing.first_player_wins <= pairing.games());
+        }
+        // The greedy bot beats the random ones
+        let standings = results.standings(4);
+        assert_eq!(standings[0].player, 0);
+        assert_eq!(standings[0].matches, [3, 0, 0]);
+        // Going first doesn't decide the games
+        let first_player_wins: u32 = results.pairings.iter().map(|p| p.first_player_wins).sum();
+        let games: u32 = results.pairings.iter().map(|p| p.games()).sum();
+        assert!(first_player_wins > 0 && first_player_wins < games);
+
+        // Same seed, same tournament
+        assert_eq!(run_bots(&config), results);
+    }
+
+    #[test]
+    fn test_swiss() {
+        let config = config("swiss", 5);
+        let results = run_bots(&config);
+        // 3 rounds of 2 matches and a bye
+        assert_eq!(results.pairings.len(), 6);
+        assert_eq!(results.byes.len(), 3);
+        let mut byes: Vec<usize> = results.byes.iter().map(|&(_, player)| player).collect();
+        byes.sort_unstable();
+        byes.dedup();
+        assert_eq!(byes.len(), 3);
+        let standings = results.standings(5);
+        assert_eq!(standings.iter().map(|s| s.matches.iter().sum::<u32>()).sum::<u32>(), 15);
+
+        let bot = "[[players]]\nname = \"a\"\nstrategy = \"greedy\"\n";
+        let other = "[[players]]\nname = \"b\"\nstrategy = \"random\"\n";
+        let toml = |head: &str, players: &str| Config::from_toml(&format!("{}{}{}", head, STRENGTHS, players));
+        assert!(toml("format = \"swiss\"\n", bot).is_err());
+        assert!(toml("format = \"swiss\"\n", &format!("{}{}", bot, bot)).is_err());
+        assert!(toml("format = \"swiss\"\nrounds = 2\n", &format!("{}{}", bot, other)).is_ok());
+        // Rounds are only for Swiss tournaments
+        assert!(toml("format = \"round_robin\"\nrounds = 2\n", &format!("{}{}", bot, other)).is_err());
+        // Games have to be decided by combat
+        let players = format!("{}{}", bot, other);
+        assert!(Config::from_toml(&format!("format = \"swiss\"\n{}", players)).is_err());
+    }
+
+    #[test]
+    fn test_humans() {
+        let mut config = config("round_robin", 3);
+        config.players.push(Participant { name: "human".to_owned(), strategy: None });
+        // The human wins every game, whichever seat they are in
+        let mut games = 0;
+        let results = run(&config, |_, _, seats: [&Participant; 2]| -> Result<Played, ()> {
+            games += 1;
+            let human = seats.iter().position(|p| p.strategy.is_none()).unwrap();
+            Ok(Played { outcome: Outcome::Win(human as u32), turns: 10 })
+        }).unwrap();
+        // 3 matches against the bots, won in 2 games
+        assert_eq!(games, 6);
+        let standings = results.standings(4);
+        assert_eq!(standings[0].player, 3);
+        assert_eq!((standings[0].matches, standings[0].games), ([3, 0, 0], [6, 0, 0]));
+        let pairing = results.pairings.iter().find(|p| p.players.contains(&3)).unwrap();
+        assert_eq!(pairing.turns, 20);
+
+        // The host's errors stop the tournament
+        assert_eq!(run(&config, |_, _, _| Err("quit")), Err("quit"));
+    }
+}
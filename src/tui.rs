@@ -0,0 +1,703 @@
+//! Full-screen terminal interface
+//!
+//! Up and down move between the enemy's creatures, your creatures, your gems
+//! and your hand, left and right move between cards. Enter acts on the
+//! selected card, space selects gems when paying, `p` passes, `u` takes back
+//! the last action when the rules allow it, page up and page down scroll the
+//! log, and `q` quits.
+
+use rand::{Rng, thread_rng};
+use ratatui::{DefaultTerminal, Frame};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color as TermColor, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use superjack::deck::DeckList;
+use superjack::game::{
+    Action, Card, Color, Creature, CreatureStatus, Event, Game, Gem, Outcome,
+    Phase, PickCards, PlayerView,
+};
+use superjack::pay;
+use superjack::replay::Replay;
+use superjack::rules::RuleSet;
+
+use crate::Error;
+use crate::cli::describe_event;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zone {
+    Enemy,
+    Creatures,
+    Gems,
+    Hand,
+}
+
+impl Zone {
+    fn up(self) -> Zone {
+        match self {
+            Zone::Enemy => Zone::Enemy,
+            Zone::Creatures => Zone::Enemy,
+            Zone::Gems => Zone::Creatures,
+            Zone::Hand => Zone::Gems,
+        }
+    }
+
+    fn down(self) -> Zone {
+        match self {
+            Zone::Enemy => Zone::Creatures,
+            Zone::Creatures => Zone::Gems,
+            Zone::Gems => Zone::Hand,
+            Zone::Hand => Zone::Hand,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    /// Selecting gems to pay for a card from the hand
+    Pay { card: usize, selected: Vec<usize> },
+    /// Selecting the attacker to block
+    Block { creature: usize },
+    /// Waiting for `p` to be pressed again, passing ends the phase
+    Pass,
+}
+
+pub struct App {
+    game: Game,
+    zone: Zone,
+    cursor: usize,
+    mode: Mode,
+    log: Vec<Event>,
+    /// How many lines the log is scrolled back
+    scroll: usize,
+    message: String,
+    /// Whether two players share the screen and shouldn't see each other's
+    /// cards
+    hotseat: bool,
+    /// The player whose cards are on the screen, in hotseat mode
+    shown: Option<u32>,
+    replay: Replay,
+}
+
+fn card_span(card: &Card, style: Style) -> Span<'static> {
+    let style = match card.color() {
+        Color::Red => style.fg(TermColor::Red),
+        Color::Black => style,
+    };
+    Span::styled(card.to_string(), style)
+}
+
+impl App {
+    pub fn new(game: Game, hotseat: bool) -> App {
+        let mut app = App {
+            replay: Replay::new(&game),
+            game,
+            zone: Zone::Hand,
+            cursor: 0,
+            mode: Mode::Normal,
+            log: Vec::new(),
+            scroll: 0,
+            message: String::new(),
+            hotseat,
+            shown: None,
+        };
+        let events = app.game.start();
+        app.record(&events);
+        app.auto_pass();
+        app
+    }
+
+    fn status(&self) -> PlayerView {
+        self.game.view_for(self.game.priority())
+    }
+
+    fn zone_len(&self, zone: Zone) -> usize {
+        let status = self.status();
+        match zone {
+            Zone::Enemy => status.enemy_creatures.len(),
+            Zone::Creatures => status.creatures.len(),
+            Zone::Gems => status.gems.len(),
+            Zone::Hand => status.hand.len(),
+        }
+    }
+
+    fn record(&mut self, events: &[Event]) {
+        self.log.extend(events.iter().cloned());
+        self.scroll = 0;
+    }
+
+    /// The log, as seen by the given player
+    fn log_lines(&self, viewer: u32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for event in &self.log {
+            let text = if self.hotseat {
+                describe_event(&event.redact_for(viewer), viewer)
+            } else {
+                describe_event(event, viewer)
+            };
+            lines.extend(text.lines().filter(|l| !l.is_empty()).map(|l| l.to_owned()));
+        }
+        if let Some(outcome) = self.game.outcome() {
+            lines.push(outcome.to_string());
+        }
+        lines
+    }
+
+    /// Whether the screen is hiding the cards until the player is ready
+    fn covered(&self) -> bool {
+        self.hotseat && self.shown != Some(self.game.priority()) && self.game.outcome().is_none()
+    }
+
+    /// Pass phases where the only thing to do is to pass
+    fn auto_pass(&mut self) {
+        while self.game.phase != Phase::Main
+            && self.game.legal_actions(self.game.priority()) == [Action::Pass]
+        {
+            let player = self.game.priority();
+            let events = self.game.apply(player, Action::Pass).unwrap();
+            self.replay.record(player, Action::Pass);
+            self.record(&events);
+        }
+    }
+
+    fn submit(&mut self, action: Action) {
+        let player = self.game.priority();
+        match self.game.apply(player, action.clone()) {
+            Ok(events) => {
+                self.replay.record(player, action);
+                self.record(&events);
+                self.message.clear();
+                self.mode = Mode::Normal;
+                self.auto_pass();
+            }
+            Err(e) => self.message = e.to_string(),
+        }
+        self.cursor = self.cursor.min(self.zone_len(self.zone).saturating_sub(1));
+    }
+
+    fn select_zone(&mut self, zone: Zone) {
+        self.zone = zone;
+        self.cursor = self.cursor.min(self.zone_len(zone).saturating_sub(1));
+    }
+
+    /// Handle a key, returns the action to submit if one was picked
+    fn key(&mut self, key: KeyCode) -> Result<Option<Action>, Error> {
+        if self.covered() {
+            match key {
+                KeyCode::Char('q') => return Err(Error::Exit),
+                KeyCode::Enter => self.shown = Some(self.game.priority()),
+                _ => {}
+            }
+            return Ok(None);
+        }
+        match key {
+            KeyCode::Char('q') => return Err(Error::Exit),
+            KeyCode::Up => self.select_zone(self.zone.up()),
+            KeyCode::Down => self.select_zone(self.zone.down()),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right if self.cursor + 1 < self.zone_len(self.zone) => self.cursor += 1,
+            KeyCode::PageUp => {
+                let lines = self.log_lines(self.game.priority()).len();
+                self.scroll = (self.scroll + 5).min(lines.saturating_sub(1));
+            }
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(5),
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.message.clear();
+            }
+            KeyCode::Char(' ') => {
+                if let (Mode::Pay { ref mut selected, .. }, Zone::Gems) = (&mut self.mode, self.zone) {
+                    match selected.iter().position(|&g| g == self.cursor) {
+                        Some(i) => {
+                            selected.remove(i);
+                        }
+                        None => selected.push(self.cursor),
+                    }
+                }
+            }
+            KeyCode::Char('p') if self.mode == Mode::Normal => {
+                self.mode = Mode::Pass;
+                self.message = format!("Press p again to pass, ending the {}", self.game.phase);
+            }
+            KeyCode::Char('p') if self.mode == Mode::Pass => return Ok(Some(Action::Pass)),
+            KeyCode::Char('u') if self.mode == Mode::Normal && self.game.rules.undo => {
+                return Ok(Some(Action::Undo));
+            }
+            KeyCode::Enter => return Ok(self.activate()),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn activate(&mut self) -> Option<Action> {
+        if self.cursor >= self.zone_len(self.zone) {
+            return None;
+        }
+        let status = self.status();
+        match (&self.mode, self.zone, status.phase) {
+            (&Mode::Pay { card, ref selected }, _, _) => {
+                let mut gems = selected.clone();
+                gems.sort_unstable();
+                Some(Action::PlayCreature { card, gems })
+            }
+            (&Mode::Block { creature }, Zone::Enemy, _) => {
+                Some(Action::Block { creature, attacker: self.cursor })
+            }
+            (Mode::Normal, Zone::Hand, Phase::Main) => {
+                let card = &status.hand[self.cursor];
+                match status.rules.cost(card.face) {
+                    None => Some(Action::PlayGem { card: self.cursor }),
+                    Some(cost) => {
+                        let color = card.color();
+                        let selected = pay::suggest(&status.gems, cost, color, &status.rules.payment)
+                            .unwrap_or_default();
+                        self.message = format!(
+                            "Press enter to pay with the suggested gems, or pick {} {} gems with space",
+                            cost,
+                            color,
+                        );
+                        self.mode = Mode::Pay { card: self.cursor, selected };
+                        self.zone = Zone::Gems;
+                        self.cursor = status.gems.iter()
+                            .position(|g| !g.tapped && g.card.color() == color)
+                            .unwrap_or(0);
+                        None
+                    }
+                }
+            }
+            (Mode::Normal, Zone::Creatures, Phase::Attack) => {
+                Some(Action::Attack { creature: self.cursor })
+            }
+            (Mode::Normal, Zone::Creatures, Phase::Defend) => {
+                self.mode = Mode::Block { creature: self.cursor };
+                self.message = "Pick the attacker to block".to_owned();
+                self.zone = Zone::Enemy;
+                self.cursor = status.attackers.first().copied().unwrap_or(0);
+                None
+            }
+            _ => {
+                self.message = "You can't use this now".to_owned();
+                None
+            }
+        }
+    }
+
+    fn style(&self, zone: Zone, i: usize) -> Style {
+        if self.zone == zone && self.cursor == i {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        }
+    }
+
+    fn gems_line(&self, label: &str, gems: &[Gem], zone: Option<Zone>) -> Line<'static> {
+        let selected: &[usize] = match self.mode {
+            Mode::Pay { ref selected, .. } if zone.is_some() => selected,
+            _ => &[],
+        };
+        let mut spans = vec![Span::raw(format!("{:<11}", label))];
+        let mut prev_color = None;
+        for (i, gem) in gems.iter().enumerate() {
+            if prev_color != Some(gem.card.color()) {
+                prev_color = Some(gem.card.color());
+                spans.push(Span::raw(format!("{}: ", gem.card.color())));
+            }
+            let mut style = zone.map(|z| self.style(z, i)).unwrap_or_default();
+            if gem.tapped {
+                style = style.add_modifier(Modifier::DIM);
+            }
+            if selected.contains(&i) {
+                spans.push(Span::raw("["));
+                spans.push(card_span(&gem.card, style));
+                spans.push(Span::raw("] "));
+            } else {
+                spans.push(card_span(&gem.card, style));
+                spans.push(Span::raw(if gem.tapped { "T " } else { "  " }));
+            }
+        }
+        if gems.is_empty() {
+            spans.push(Span::raw("(no gems)"));
+        }
+        Line::from(spans)
+    }
+
+    fn creatures_line(&self, label: &str, creatures: &[Creature], zone: Zone, attacking: &[usize]) -> Line<'static> {
+        let mut spans = vec![Span::raw(format!("{:<11}", label))];
+        for (i, creature) in creatures.iter().enumerate() {
+            let style = self.style(zone, i);
+            for card in &creature.cards {
+                spans.push(card_span(card, style));
+            }
+            let mut status = match creature.status {
+                CreatureStatus::Ready => "ready",
+                CreatureStatus::Tapped => "tapped",
+                CreatureStatus::Untrained => "untrained",
+            }.to_owned();
+            if creature.royal_charge {
+                status.push_str(", royal charge");
+            }
+            if attacking.contains(&i) {
+                status.push_str(", attacking");
+            }
+            spans.push(Span::raw(format!(" ({})  ", status)));
+        }
+        if creatures.is_empty() {
+            spans.push(Span::raw("(no creatures)"));
+        }
+        Line::from(spans)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        if self.covered() {
+            draw_cover(frame, self.game.priority());
+            return;
+        }
+        let status = self.status();
+        let [header, enemy, us, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(4),
+            Constraint::Length(5),
+            Constraint::Min(5),
+        ]).areas(frame.area());
+        let [log, help_area] = Layout::horizontal([
+            Constraint::Percentage(60),
+            Constraint::Percentage(40),
+        ]).areas(bottom);
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Player {}'s turn, {} - Player {} to act",
+                status.current_player + 1,
+                status.phase,
+                status.player + 1,
+            )).style(Style::default().add_modifier(Modifier::BOLD)),
+            header,
+        );
+
+        // The current player's attackers, or the enemy's blockers
+        let (enemy_marked, our_marked): (Vec<usize>, Vec<usize>) = if status.current_player == status.player {
+            (status.blockers.iter().map(|&(b, _)| b).collect(), status.attackers.clone())
+        } else {
+            (status.attackers.clone(), status.blockers.iter().map(|&(b, _)| b).collect())
+        };
+
+        let enemy_lines = vec![
+            self.gems_line("Gems", &status.enemy_gems, None),
+            self.creatures_line("Creatures", &status.enemy_creatures, Zone::Enemy, &enemy_marked),
+        ];
+        frame.render_widget(
+            Paragraph::new(enemy_lines).block(Block::default().borders(Borders::ALL).title(format!(
+                "Enemy - life {} - {} cards in hand - library {} - graveyard {}",
+                status.enemy_life,
+                status.enemy_hand,
+                status.enemy_library,
+                status.enemy_graveyard.len(),
+            ))),
+            enemy,
+        );
+
+        let mut hand = vec![Span::raw(format!("{:<11}", "Hand"))];
+        for (i, card) in status.hand.iter().enumerate() {
+            hand.push(card_span(card, self.style(Zone::Hand, i)));
+            hand.push(Span::raw("  "));
+        }
+        let our_lines = vec![
+            self.creatures_line("Creatures", &status.creatures, Zone::Creatures, &our_marked),
+            self.gems_line("Gems", &status.gems, Some(Zone::Gems)),
+            Line::from(hand),
+        ];
+        frame.render_widget(
+            Paragraph::new(our_lines).block(Block::default().borders(Borders::ALL).title(format!(
+                "Player {} - life {} - library {} - graveyard {}",
+                status.player + 1,
+                status.life,
+                status.library,
+                status.graveyard.len(),
+            ))),
+            us,
+        );
+
+        let log_lines = self.log_lines(status.player);
+        let height = log.height.saturating_sub(2) as usize;
+        let end = log_lines.len() - self.scroll.min(log_lines.len());
+        let start = end.saturating_sub(height);
+        let lines: Vec<Line> = log_lines[start..end].iter().map(|l| Line::raw(l.as_str())).collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Log")),
+            log,
+        );
+
+        let mut help = vec![Line::raw(self.message.as_str()), Line::raw("")];
+        if self.game.outcome().is_some() {
+            help.push(Line::raw("Game over, press any key"));
+        } else {
+            help.extend([
+                "arrows: select",
+                "enter: play or use",
+                "space: pick gem",
+                "p twice: pass    esc: cancel",
+                "pgup/pgdn: scroll log",
+                "q: quit",
+            ].iter().map(|&l| Line::raw(l)));
+            if self.game.rules.undo {
+                help.push(Line::raw("u: undo"));
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(help)
+                .wrap(Wrap { trim: true })
+                .block(Block::default().borders(Borders::ALL).title("Help")),
+            help_area,
+        );
+    }
+}
+
+/// Hide the screen until the given player is ready, in hotseat mode
+fn draw_cover(frame: &mut Frame, player: u32) {
+    frame.render_widget(
+        Paragraph::new(format!("Player {} press enter", player + 1))
+            .block(Block::default().borders(Borders::ALL)),
+        frame.area(),
+    );
+}
+
+/// Show a player their starting hand and let them Mulligan
+fn pick_starting_hand<R: Rng>(
+    terminal: &mut DefaultTerminal,
+    player: u32,
+    picks: &mut PickCards,
+    hotseat: bool,
+    rng: &mut R,
+) -> Result<(), Error> {
+    if hotseat {
+        terminal.draw(|frame| draw_cover(frame, player))?;
+        loop {
+            if let TermEvent::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char('q') => return Err(Error::Exit),
+                    KeyCode::Enter if key.kind == KeyEventKind::Press => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+    loop {
+        let can_mulligan = picks.can_mulligan(player);
+        terminal.draw(|frame| {
+            let mut spans = Vec::new();
+            for card in picks.hand(player) {
+                spans.push(card_span(card, Style::default()));
+                spans.push(Span::raw("  "));
+            }
+            let lines = vec![
+                Line::from(spans),
+                Line::raw(""),
+                Line::raw(if can_mulligan { "m: Mulligan    enter: keep" } else { "enter: continue" }),
+            ];
+            frame.render_widget(
+                Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(
+                    format!("Player {} draws cards", player + 1),
+                )),
+                frame.area(),
+            );
+        })?;
+        loop {
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') => return Err(Error::Exit),
+                    KeyCode::Char('m') if can_mulligan => {
+                        picks.mulligan(player, rng).unwrap();
+                        break;
+                    }
+                    KeyCode::Enter => {
+                        if !picks.is_ready(player) {
+                            picks.accept(player).unwrap();
+                        }
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    hotseat: bool,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    record: Option<&str>,
+) -> Result<Outcome, Error> {
+    let mut rng = thread_rng();
+    let mut picks = PickCards::new(decks, rules.clone(), &mut rng);
+    pick_starting_hand(terminal, 0, &mut picks, hotseat, &mut rng)?;
+    pick_starting_hand(terminal, 1, &mut picks, hotseat, &mut rng)?;
+    let mut app = App::new(picks.start().unwrap(), hotseat);
+    loop {
+        if let Some(path) = record {
+            std::fs::write(path, app.replay.to_json())?;
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        if let TermEvent::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(outcome) = app.game.outcome() {
+                return Ok(outcome);
+            }
+            if let Some(action) = app.key(key.code)? {
+                app.submit(action);
+            }
+        }
+    }
+}
+
+/// Play a game with two players sharing this terminal
+///
+/// In hotseat mode, the screen is hidden before showing a player's cards.
+pub fn play(
+    hotseat: bool,
+    decks: &[DeckList; 2],
+    rules: &RuleSet,
+    record: Option<&str>,
+) -> Result<Outcome, Error> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, hotseat, decks, rules, record);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyCode;
+    use superjack::game::{Action, Card, Event, Face, Game, Player, Suit, deal};
+
+    use super::{App, Mode, Zone};
+
+    fn new_app(hotseat: bool) -> App {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (library1, hand1) = deal(0, &mut rng);
+        let (library2, hand2) = deal(1, &mut rng);
+        let mut app = App::new(
+            Game::new([Player::new(library1, hand1), Player::new(library2, hand2)]),
+            hotseat,
+        );
+        app.game.players[0].hand = vec![
+            Card { player: 0, suit: Suit::Hearts, face: Face::Four },
+            Card { player: 0, suit: Suit::Hearts, face: Face::Jack },
+        ];
+        app
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut app = new_app(false);
+        app.game.players[0].add_gem(Card { player: 0, suit: Suit::Diamonds, face: Face::Two });
+
+        // Play the gem, the hand is [4♥, J♥]
+        assert_eq!(app.zone, Zone::Hand);
+        assert_eq!(app.key(KeyCode::Enter).unwrap(), Some(Action::PlayGem { card: 0 }));
+        app.submit(Action::PlayGem { card: 0 });
+
+        // Play the jack, both gems are suggested
+        assert_eq!(app.key(KeyCode::Enter).unwrap(), None);
+        assert_eq!(app.mode, Mode::Pay { card: 0, selected: vec![0, 1] });
+        assert_eq!(app.zone, Zone::Gems);
+
+        // Unselect a gem and select it again
+        app.key(KeyCode::Char(' ')).unwrap();
+        assert_eq!(app.mode, Mode::Pay { card: 0, selected: vec![1] });
+        app.key(KeyCode::Char(' ')).unwrap();
+        let action = app.key(KeyCode::Enter).unwrap().unwrap();
+        assert_eq!(action, Action::PlayCreature { card: 0, gems: vec![0, 1] });
+        app.submit(action);
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.game.players[0].creatures.len(), 1);
+        assert!(app.log_lines(0).iter().any(|l| l.starts_with("You add creature J")));
+
+        // Passing takes a second press, and can be cancelled
+        assert_eq!(app.key(KeyCode::Char('p')).unwrap(), None);
+        assert_eq!(app.mode, Mode::Pass);
+        assert_eq!(app.message, "Press p again to pass, ending the main phase");
+        app.key(KeyCode::Esc).unwrap();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.key(KeyCode::Char('p')).unwrap(), None);
+        assert_eq!(app.key(KeyCode::Char('p')).unwrap(), Some(Action::Pass));
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = new_app(false);
+        app.game.players[0].add_gem(Card { player: 0, suit: Suit::Spades, face: Face::Two });
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal.backend().buffer().content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(screen.contains("Enemy - life 20 - 5 cards in hand - library 35 - graveyard 0"));
+        assert!(screen.contains("Player 1's turn, main phase - Player 1 to act"));
+        assert!(screen.contains("You draw a card"));
+        assert!(screen.contains("Gems       black: 2\u{2660}"));
+    }
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal.backend().buffer().content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn test_hotseat() {
+        let mut app = new_app(true);
+        assert!(screen(&app).contains("Player 1 press enter"));
+        assert!(!screen(&app).contains("Hand"));
+        assert_eq!(app.key(KeyCode::Char('p')).unwrap(), None);
+        app.key(KeyCode::Enter).unwrap();
+        assert!(screen(&app).contains("Hand"));
+
+        // End the turn, player 2 draws at the hand-over
+        assert_eq!(app.key(KeyCode::Char('p')).unwrap(), None);
+        let action = app.key(KeyCode::Char('p')).unwrap().unwrap();
+        app.submit(action);
+        assert_eq!(app.game.current_player, 1);
+        let drawn: Vec<(u32, Card)> = app.log.iter()
+            .filter_map(|e| match *e {
+                Event::CardDrawn { player, card: Some(ref card) } => Some((player, card.clone())),
+                _ => None,
+            })
+            .collect();
+        let (player, ref card) = *drawn.last().unwrap();
+        assert_eq!(player, 1);
+        let line = format!("You draw a card: {}", card);
+
+        // It's hidden until player 2 is there
+        assert!(screen(&app).contains("Player 2 press enter"));
+        assert!(!screen(&app).contains(&line));
+        app.key(KeyCode::Enter).unwrap();
+        assert!(screen(&app).contains(&line));
+
+        // Each player sees their own draws, and only that the enemy drew
+        for (player, card) in drawn {
+            let line = format!("You draw a card: {}", card);
+            assert!(app.log_lines(player).contains(&line));
+            let enemy = app.log_lines(1 - player);
+            assert!(!enemy.contains(&line));
+            assert!(enemy.contains(&"Enemy draws a card".to_owned()));
+            assert!(!enemy.iter().any(|l| l.starts_with("Enemy draws a card:")));
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Lessons teaching the rules, one step at a time
+//!
+//! Each lesson starts from a prepared position. At every step, an explanation
+//! is shown and the player can only take the actions the step asks for, while
+//! the enemy follows a script. Phases where the step has nothing to do are
+//! passed automatically.
+//!
+//! The lessons cover gems and colors, summoning, Royal Charge and combat.
+//! Instant actions, straights and sacrifices are not taught, since the engine
+//! doesn't support them yet, and the last lesson tells the player so.
+
+use std::collections::VecDeque;
+
+use crate::game::{Action, CreatureStatus, Event, Game, RuleError};
+use crate::scenario::Scenario;
+
+/// The player following the tutorial, the enemy is player 2
+pub const PLAYER: u32 = 0;
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub text: &'static str,
+    /// The actions that complete the step
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lesson {
+    pub title: &'static str,
+    pub intro: &'static str,
+    pub game: Game,
+    pub steps: Vec<Step>,
+    /// What the enemy does when they have priority, they pass once this runs
+    /// out
+    pub enemy: Vec<Action>,
+    pub conclusion: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TutorialError {
+    /// The rules don't allow this action
+    Rule(RuleError),
+    /// The action is allowed, but it's not what this step is about
+    NotInLesson,
+    Finished,
+}
+
+impl std::fmt::Display for TutorialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TutorialError::Rule(ref e) => write!(f, "{}", e),
+            TutorialError::NotInLesson => write!(f, "You could, but let's follow the lesson"),
+            TutorialError::Finished => write!(f, "This lesson is over"),
+        }
+    }
+}
+
+impl std::error::Error for TutorialError {}
+
+/// A lesson being played
+#[derive(Debug, Clone)]
+pub struct Tutorial {
+    lesson: Lesson,
+    game: Game,
+    step: usize,
+    enemy: VecDeque<Action>,
+}
+
+impl Tutorial {
+    pub fn new(lesson: Lesson) -> Tutorial {
+        let mut tutorial = Tutorial {
+            game: lesson.game.clone(),
+            enemy: lesson.enemy.iter().cloned().collect(),
+            lesson,
+            step: 0,
+        };
+        tutorial.advance();
+        tutorial
+    }
+
+    pub fn lesson(&self) -> &Lesson {
+        &self.lesson
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The current step, or None once the lesson is over
+    pub fn step(&self) -> Option<&Step> {
+        self.lesson.steps.get(self.step)
+    }
+
+    /// Take an action for the player
+    ///
+    /// The events include what the enemy did and the phases that were passed
+    /// automatically after it.
+    pub fn apply(&mut self, action: Action) -> Result<Vec<Event>, TutorialError> {
+        let step = self.step().ok_or(TutorialError::Finished)?;
+        // Explain what the rules say first, which is the point of the lesson
+        self.game.check(PLAYER, &action).map_err(TutorialError::Rule)?;
+        if !step.actions.contains(&action) {
+            return Err(TutorialError::NotInLesson);
+        }
+        let mut events = self.game.apply(PLAYER, action).unwrap();
+        self.step += 1;
+        events.extend(self.advance());
+        Ok(events)
+    }
+
+    /// Play the enemy, and pass for the player until the step can be done
+    ///
+    /// After the last step, this goes on until the end of combat.
+    fn advance(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        while self.game.outcome().is_none() && self.game.current_player == PLAYER {
+            let player = self.game.priority();
+            let action = if player != PLAYER {
+                self.enemy.pop_front().unwrap_or(Action::Pass)
+            } else {
+                let legal = self.game.legal_actions(player);
+                match self.lesson.steps.get(self.step) {
+                    Some(step) if step.actions.iter().any(|a| legal.contains(a)) => break,
+                    None if self.game.attackers.is_empty() => break,
+                    _ => Action::Pass,
+                }
+            };
+            events.extend(self.game.apply(player, action).unwrap());
+        }
+        events
+    }
+}
+
+/// Summon a creature with the gems at those positions
+fn summon(card: usize, gems: &[usize]) -> Action {
+    Action::PlayCreature { card, gems: gems.to_vec() }
+}
+
+pub fn lessons() -> Vec<Lesson> {
+    vec![
+        Lesson {
+            title: "Gems and colors",
+            intro: "\
+Cards from 2 to 7 are gems. They are played on the board, where they pay for
+summoning creatures. Hearts and diamonds are red, spades and clubs are black,
+and creatures are paid with gems of their own color.",
+            game: Scenario::new()
+                .hand(PLAYER, "2♥ 5♠ J♥")
+                .gems(PLAYER, "3♥")
+                .build(),
+            steps: vec![
+                Step {
+                    text: "\
+Play the 2♥ as a gem (card 1). You can only play one gem per turn, so pick
+the one you will need: your Jack of hearts needs red gems.",
+                    actions: vec![Action::PlayGem { card: 0 }],
+                },
+                Step {
+                    text: "\
+You now have 2 red gems. Pass to end your main phase, your 5♠ will wait for
+your next turn.",
+                    actions: vec![Action::Pass],
+                },
+            ],
+            enemy: Vec::new(),
+            conclusion: "Next turn, those two red gems can summon your Jack.",
+        },
+        Lesson {
+            title: "Summoning Jacks",
+            intro: "\
+Face cards are creatures. Summoning one taps gems of its color: 2 for a Jack.
+Tapped gems come back at the start of your next turn.",
+            game: Scenario::new()
+                .hand(PLAYER, "J♥ J♠")
+                .gems(PLAYER, "2♥ 3♥ 5♦ 4♠")
+                .build(),
+            steps: vec![
+                Step {
+                    text: "\
+Summon the J♥ (card 1). When the gems you tap form a straight, such as 2♥ 3♥,
+and pay the exact cost, the creature gets Royal Charge. Pay with gems 1 and 2.",
+                    actions: vec![summon(0, &[0, 1])],
+                },
+                Step {
+                    text: "\
+Your J♠ needs 2 black gems, and you only have one: try it to see what the
+rules say, then pass.",
+                    actions: vec![Action::Pass],
+                },
+            ],
+            enemy: Vec::new(),
+            conclusion: "\
+New creatures are Untrained: they can't attack until your next turn.",
+        },
+        Lesson {
+            title: "Queens, Kings and Aces",
+            intro: "\
+Bigger creatures cost more: 4 gems for a Queen, 5 for a King and 3 for an
+Ace. Aces can also be played as spells, which this game doesn't support yet.",
+            game: Scenario::new()
+                .hand(PLAYER, "K♠ Q♥ A♣")
+                .gems(PLAYER, "2♠ 3♠ 4♠ 5♠ 6♠ 7♣")
+                .build(),
+            steps: vec![
+                Step {
+                    text: "\
+Summon the K♠ (card 2) with the straight of spades from 2 to 6, gems 1 to 5.
+Your Q♥ can't be summoned, you have no red gems.",
+                    actions: vec![summon(1, &[0, 1, 2, 3, 4])],
+                },
+                Step {
+                    text: "\
+Your Ace needs 3 black gems and only the 7♣ is left untapped. Pass.",
+                    actions: vec![Action::Pass],
+                },
+            ],
+            enemy: Vec::new(),
+            conclusion: "\
+Spending all your gems on one big creature, or several small ones, is the
+choice you will make every turn.",
+        },
+        Lesson {
+            title: "Combat",
+            intro: "\
+In the attack phase, your ready creatures can attack. The enemy then picks
+which of their creatures block which attacker. The rules don't say how much
+damage creatures deal yet, so for now this game stops there: fights don't
+destroy creatures, and attackers that are not blocked don't damage the enemy.",
+            game: Scenario::new()
+                .creature(PLAYER, "Q♥", CreatureStatus::Ready)
+                .creature(PLAYER, "J♠", CreatureStatus::Ready)
+                .creature(1, "J♣", CreatureStatus::Ready)
+                .build(),
+            steps: vec![
+                Step {
+                    text: "Pass to move on to the attack phase.",
+                    actions: vec![Action::Pass],
+                },
+                Step {
+                    text: "Attack with your J♠ (creature 1).",
+                    actions: vec![Action::Attack { creature: 0 }],
+                },
+                Step {
+                    text: "And with your Q♥ (creature 2).",
+                    actions: vec![Action::Attack { creature: 1 }],
+                },
+                Step {
+                    text: "Pass to let the enemy block. Their J♣ will block your J♠.",
+                    actions: vec![Action::Pass],
+                },
+            ],
+            enemy: vec![Action::Block { creature: 0, attacker: 0 }],
+            conclusion: "\
+Attacking creatures are tapped, and stay tapped until your next turn: they
+can't block during the enemy's turn. You know enough to play your first game.
+Good luck!
+
+Instant actions, straights and sacrifices are part of the rules too, but this
+game doesn't support them yet, so there are no lessons about them.",
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Action, Event, Phase, RuleError};
+
+    use super::{Tutorial, TutorialError, lessons};
+
+    #[test]
+    fn test_lessons() {
+        // Every lesson can be completed
+        for lesson in lessons() {
+            let title = lesson.title;
+            let mut tutorial = Tutorial::new(lesson);
+            while let Some(step) = tutorial.step() {
+                let action = step.actions[0].clone();
+                tutorial.apply(action).unwrap_or_else(|e| panic!("{}: {}", title, e));
+            }
+            assert_eq!(tutorial.apply(Action::Pass), Err(TutorialError::Finished));
+        }
+    }
+
+    #[test]
+    fn test_constrained() {
+        let mut tutorial = Tutorial::new(lessons().remove(1));
+        assert_eq!(
+            tutorial.apply(Action::PlayCreature { card: 1, gems: vec![3] }),
+            Err(TutorialError::Rule(RuleError::WrongGemCount { cost: 2 })),
+        );
+        // Legal, but not what the lesson asks
+        assert_eq!(
+            tutorial.apply(Action::PlayCreature { card: 0, gems: vec![0, 2] }),
+            Err(TutorialError::NotInLesson),
+        );
+        assert!(tutorial.game().players[0].creatures.is_empty());
+        let events = tutorial.apply(Action::PlayCreature { card: 0, gems: vec![0, 1] }).unwrap();
+        assert!(matches!(events[..], [Event::CreaturePlayed { royal_charge: true, .. }]));
+    }
+
+    #[test]
+    fn test_combat() {
+        let mut tutorial = Tutorial::new(lessons().remove(3));
+        tutorial.apply(Action::Pass).unwrap();
+        tutorial.apply(Action::Attack { creature: 0 }).unwrap();
+        tutorial.apply(Action::Attack { creature: 1 }).unwrap();
+        // The enemy blocks, and the instant phases are passed
+        let events = tutorial.apply(Action::Pass).unwrap();
+        assert!(events.contains(&Event::BlockerDeclared { player: 1, creature: 0, attacker: 0 }));
+        assert_eq!(tutorial.game().players[1].life, 20);
+        assert_eq!(tutorial.game().phase, Phase::SecondMain);
+        assert!(tutorial.step().is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! JavaScript bindings, for running games in the browser
+//!
+//! Values are exchanged as JSON strings, in the same format as the servers
+//! use. Errors are returned as exceptions holding a message.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::deck::DeckList;
+use crate::game::{Action, Game, PickCards};
+use crate::rules::RuleSet;
+
+#[wasm_bindgen(js_name = Game)]
+pub struct JsGame {
+    rng: StdRng,
+    picks: PickCards,
+    game: Option<Game>,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl JsGame {
+    /// Deal starting hands, using a random generator seeded by the caller
+    ///
+    /// The rules are given as JSON, in the same format as rules files, and
+    /// each deck as a deck list. Missing ones are the default rules and the
+    /// standard deck.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        seed: u64,
+        rules: Option<String>,
+        first_deck: Option<String>,
+        second_deck: Option<String>,
+    ) -> Result<JsGame, String> {
+        let rules = match rules {
+            Some(rules) => RuleSet::from_json(&rules).map_err(|e| e.to_string())?,
+            None => RuleSet::default(),
+        };
+        let mut decks = [DeckList::standard(), DeckList::standard()];
+        for (player, list) in [first_deck, second_deck].into_iter().enumerate() {
+            if let Some(list) = list {
+                let deck = DeckList::parse(&list, &rules.deck)
+                    .and_then(|deck| deck.validate(&rules.deck).map(|()| deck))
+                    .map_err(|e| format!("Deck of player {}: {}", player, e))?;
+                decks[player] = deck;
+            }
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let picks = PickCards::new(&decks, rules, &mut rng);
+        Ok(JsGame { rng, picks, game: None })
+    }
+
+    #[wasm_bindgen(js_name = startingHand)]
+    pub fn starting_hand(&self, player: u32) -> Result<String, String> {
+        if player > 1 {
+            return Err("No such player".to_owned());
+        }
+        Ok(serde_json::to_string(self.picks.hand(player)).unwrap())
+    }
+
+    /// Shuffle a player's starting hand back into their deck and draw a new
+    /// one, as many times as the rules allow
+    pub fn mulligan(&mut self, player: u32) -> Result<String, String> {
+        if player > 1 {
+            return Err("No such player".to_owned());
+        }
+        let hand = self.picks.mulligan(player, &mut self.rng).map_err(|e| e.to_string())?;
+        Ok(serde_json::to_string(hand).unwrap())
+    }
+
+    /// Keep a player's starting hand
+    pub fn accept(&mut self, player: u32) -> Result<(), String> {
+        if player > 1 {
+            return Err("No such player".to_owned());
+        }
+        self.picks.accept(player).map_err(|e| e.to_string())
+    }
+
+    /// Start playing, keeping the starting hands of players who didn't
+    /// accept them yet, returns the events
+    pub fn start(&mut self) -> Result<String, String> {
+        if self.game.is_some() {
+            return Err("Game already started".to_owned());
+        }
+        for player in 0..2 {
+            if !self.picks.is_ready(player) {
+                self.picks.accept(player).unwrap();
+            }
+        }
+        let mut game = self.picks.start().map_err(|e| e.to_string())?;
+        let events = game.start();
+        self.game = Some(game);
+        Ok(serde_json::to_string(&events).unwrap())
+    }
+
+    fn game(&self) -> Result<&Game, String> {
+        self.game.as_ref().ok_or_else(|| "Game has not started".to_owned())
+    }
+
+    /// The player who is expected to act
+    pub fn priority(&self) -> Result<u32, String> {
+        Ok(self.game()?.priority())
+    }
+
+    /// What a player can see
+    pub fn view(&self, player: u32) -> Result<String, String> {
+        if player > 1 {
+            return Err("No such player".to_owned());
+        }
+        Ok(serde_json::to_string(&self.game()?.view_for(player)).unwrap())
+    }
+
+    #[wasm_bindgen(js_name = legalActions)]
+    pub fn legal_actions(&self, player: u32) -> Result<String, String> {
+        Ok(serde_json::to_string(&self.game()?.legal_actions(player)).unwrap())
+    }
+
+    /// Have a player perform an action, returns the events
+    pub fn apply(&mut self, player: u32, action: &str) -> Result<String, String> {
+        let action: Action = serde_json::from_str(action).map_err(|e| e.to_string())?;
+        let game = self.game.as_mut().ok_or("Game has not started")?;
+        let events = game.apply(player, action).map_err(|e| e.to_string())?;
+        Ok(serde_json::to_string(&events).unwrap())
+    }
+
+    /// The result of the game, or `undefined` if it is not over
+    pub fn outcome(&self) -> Result<Option<String>, String> {
+        Ok(self.game()?.outcome().map(|o| serde_json::to_string(&o).unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsGame;
+
+    #[test]
+    fn test_bindings() {
+        let mut game = JsGame::new(3, None, None, None).unwrap();
+        assert!(game.view(0).is_err());
+        let hand = game.starting_hand(1).unwrap();
+        assert_ne!(game.mulligan(1).unwrap(), hand);
+        assert!(game.mulligan(1).is_err());
+        game.accept(0).unwrap();
+        assert!(game.accept(0).is_err());
+        game.start().unwrap();
+
+        assert_eq!(game.priority(), Ok(0));
+        assert!(game.legal_actions(0).unwrap().ends_with("\"Pass\"]"));
+        assert_eq!(game.legal_actions(1).unwrap(), "[]");
+        assert!(game.apply(1, "\"Pass\"").is_err());
+        assert_eq!(
+            game.apply(0, "\"Pass\"").unwrap(),
+            r#"[{"PhaseChanged":{"phase":"Attack"}}]"#,
+        );
+        assert_eq!(game.outcome(), Ok(None));
+    }
+
+    #[test]
+    fn test_rules_and_decks() {
+        let rules = r#"{"hand_size": 3, "deck": {"size": 4, "suits": ["Hearts"]}}"#;
+        let deck = "2 J♥\n2 2H\n";
+        let mut game = JsGame::new(3, Some(rules.to_owned()), Some(deck.to_owned()), None).unwrap();
+        let hand = game.starting_hand(0).unwrap();
+        assert_eq!(hand.matches("Hearts").count(), 3);
+        assert_eq!(game.starting_hand(1).unwrap().matches("suit").count(), 3);
+        game.start().unwrap();
+        let view = game.view(0).unwrap();
+        assert!(view.contains(r#""library":0"#), "{}", view);
+        assert!(view.contains(r#""enemy_library":37"#), "{}", view);
+
+        assert!(JsGame::new(3, Some("{\"hand\": 3}".to_owned()), None, None).is_err());
+        let error = JsGame::new(3, Some(rules.to_owned()), None, Some("2 3S\n".to_owned()));
+        assert!(error.is_err_and(|e| e.starts_with("Deck of player 1: ")));
+    }
+}
@@ -0,0 +1,797 @@
+//! HTTP and WebSocket server, the backend for a web UI
+//!
+//! Games are created, joined and played with a JSON API:
+//!
+//! * `GET /games`: list games waiting for players
+//! * `POST /games`: create a game, returns `{"game": id}`, or 503 when too
+//!   many games are hosted already
+//! * `POST /games/{id}/join`: take a seat, returns `{"player": n, "token": "..."}`
+//! * `GET /games/{id}/view?token=...`: what this player can see
+//! * `POST /games/{id}/mulligan?token=...`: body is `true` or `false`
+//! * `POST /games/{id}/actions?token=...`: body is an `Action`, returns the events
+//! * `GET /games/{id}/events?token=...`: WebSocket receiving the same
+//!   `ServerMessage`s as the TCP protocol, from the start of the game
+//!
+//! Finished games are dropped a few minutes after they end, and games nobody
+//! played for an hour are dropped as well. Requests are limited in size, and
+//! clients have a few seconds to send them.
+
+use rand::{Rng, thread_rng};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tungstenite::{Message, WebSocket};
+use tungstenite::protocol::Role;
+
+use crate::Error;
+use superjack::deck::DeckList;
+use superjack::game::{Action, Card, Event, Game, Outcome, PickCards, PlayerView};
+use crate::net::ServerMessage;
+use superjack::rules::RuleSet;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// Largest request body accepted, actions and mulligans are much smaller
+const MAX_BODY: usize = 64 * 1024;
+
+/// Most games hosted at once, counting those waiting for players
+const MAX_GAMES: usize = 1000;
+
+/// Largest request line and headers accepted, together
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 32;
+
+/// How long a client has to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long finished games are kept, so players can see the final view
+const KEEP_FINISHED: Duration = Duration::from_secs(5 * 60);
+
+/// How long games are kept when nobody does anything
+const KEEP_IDLE: Duration = Duration::from_secs(60 * 60);
+
+/// How often the WebSocket checks for messages from the client
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the WebSocket checks that the client is still there
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+struct Seat {
+    token: String,
+    /// Everything sent to this player so far, streamed by the WebSocket
+    messages: Vec<ServerMessage>,
+}
+
+struct HostedGame {
+    seats: Vec<Seat>,
+    picks: PickCards,
+    game: Option<Game>,
+    /// Last time a player did something
+    updated: Instant,
+}
+
+/// What a player sees, returned by the `view` endpoint
+#[derive(Serialize)]
+#[serde(tag = "stage")]
+enum SeatView {
+    WaitingForPlayers,
+    StartingHand { hand: Vec<Card>, can_mulligan: bool },
+    WaitingForMulligan,
+    Playing { status: PlayerView, your_turn: bool },
+    Over { status: PlayerView, outcome: Outcome },
+}
+
+impl HostedGame {
+    fn new<R: Rng>(decks: &[DeckList; 2], rules: &RuleSet, rng: &mut R) -> HostedGame {
+        HostedGame {
+            seats: Vec::new(),
+            picks: PickCards::new(decks, rules.clone(), rng),
+            game: None,
+            updated: Instant::now(),
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.game.as_ref().is_some_and(|g| g.outcome().is_some())
+    }
+
+    fn seat(&self, token: &str) -> Option<u32> {
+        self.seats.iter().position(|s| s.token == token).map(|p| p as u32)
+    }
+
+    fn join<R: Rng>(&mut self, game: u64, rng: &mut R) -> Option<(u32, String)> {
+        if self.seats.len() >= 2 {
+            return None;
+        }
+        let player = self.seats.len() as u32;
+        let token = format!("{:016x}", rng.gen::<u64>());
+        self.seats.push(Seat {
+            token: token.clone(),
+            messages: vec![
+                ServerMessage::Welcome { game, player },
+                ServerMessage::StartingHand {
+                    hand: self.picks.hand(player).to_vec(),
+                    can_mulligan: self.picks.can_mulligan(player),
+                },
+            ],
+        });
+        Some((player, token))
+    }
+
+    fn mulligan<R: Rng>(&mut self, player: u32, mulligan: bool, rng: &mut R) -> Result<(), ApiError> {
+        if mulligan {
+            let hand = self.picks.mulligan(player, rng)
+                .map_err(|e| ApiError::conflict(&e.to_string()))?
+                .to_vec();
+            let can_mulligan = self.picks.can_mulligan(player);
+            self.seats[player as usize].messages.push(ServerMessage::StartingHand { hand, can_mulligan });
+        } else {
+            self.picks.accept(player).map_err(|e| ApiError::conflict(&e.to_string()))?;
+        }
+
+        // Start the game once both players are ready
+        if self.seats.len() == 2 {
+            if let Ok(mut game) = self.picks.start() {
+                let events = game.start();
+                self.game = Some(game);
+                self.publish(events);
+            }
+        }
+        Ok(())
+    }
+
+    /// Send events to both players, then prompt the next one to act
+    fn publish(&mut self, events: Vec<Event>) {
+        let game = self.game.as_ref().unwrap();
+        for (player, seat) in self.seats.iter_mut().enumerate() {
+            let visible = events.iter().map(|e| e.redact_for(player as u32)).collect();
+            seat.messages.push(ServerMessage::Events(visible));
+        }
+        match game.outcome() {
+            Some(outcome) => {
+                for seat in &mut self.seats {
+                    seat.messages.push(ServerMessage::GameOver(outcome));
+                }
+            }
+            None => {
+                let player = game.priority();
+                self.seats[player as usize].messages.push(ServerMessage::Prompt(Box::new(game.view_for(player))));
+            }
+        }
+    }
+
+    fn view(&self, player: u32) -> SeatView {
+        match (&self.game, self.picks.is_ready(player)) {
+            (Some(game), _) => match game.outcome() {
+                Some(outcome) => SeatView::Over { status: game.view_for(player), outcome },
+                None => SeatView::Playing {
+                    status: game.view_for(player),
+                    your_turn: game.priority() == player,
+                },
+            },
+            (None, false) => SeatView::StartingHand {
+                hand: self.picks.hand(player).to_vec(),
+                can_mulligan: self.picks.can_mulligan(player),
+            },
+            (None, _) if self.seats.len() < 2 => SeatView::WaitingForPlayers,
+            (None, _) => SeatView::WaitingForMulligan,
+        }
+    }
+}
+
+struct Lobby {
+    /// The decks of the players, the first player to join using the first
+    decks: [DeckList; 2],
+    rules: RuleSet,
+    /// Games can't be created while this many are hosted
+    max_games: usize,
+    next_id: u64,
+    games: HashMap<u64, HostedGame>,
+}
+
+impl Lobby {
+    fn new(decks: &[DeckList; 2], rules: &RuleSet) -> Lobby {
+        Lobby {
+            decks: decks.clone(),
+            rules: rules.clone(),
+            max_games: MAX_GAMES,
+            next_id: 0,
+            games: HashMap::new(),
+        }
+    }
+
+    /// Drop games that are over or abandoned
+    fn purge(&mut self, now: Instant) {
+        self.games.retain(|_, game| {
+            let idle = now.saturating_duration_since(game.updated);
+            idle < KEEP_IDLE && !(game.is_over() && idle >= KEEP_FINISHED)
+        });
+    }
+}
+
+/// Lobby shared by all connections, with a condition variable notified when
+/// anything changes
+type Shared = Arc<(Mutex<Lobby>, Condvar)>;
+
+struct Request {
+    method: String,
+    path: Vec<String>,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn read<R: BufRead>(reader: &mut R) -> Result<Request, ApiError> {
+        // The request line and headers are read up to a limit
+        let mut head = reader.take(MAX_HEAD);
+        let mut read_line = |line: &mut String| {
+            line.clear();
+            head.read_line(line).map_err(ApiError::read)?;
+            if line.ends_with('\n') {
+                Ok(())
+            } else if head.limit() == 0 {
+                Err(ApiError::too_large())
+            } else {
+                Err(ApiError::bad_request("Connection closed"))
+            }
+        };
+
+        let mut line = String::new();
+        read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(m), Some(t)) => (m.to_owned(), t.to_owned()),
+            _ => return Err(ApiError::bad_request("Invalid HTTP request")),
+        };
+
+        let mut headers = HashMap::new();
+        loop {
+            read_line(&mut line)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if headers.len() >= MAX_HEADERS {
+                return Err(ApiError::too_large());
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+            }
+        }
+
+        let length = match headers.get("content-length") {
+            Some(l) => l.parse().map_err(|_| ApiError::bad_request("Invalid Content-Length"))?,
+            None => 0,
+        };
+        if length > MAX_BODY {
+            return Err(ApiError::payload_too_large());
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).map_err(ApiError::read)?;
+
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let path = path.split('/').filter(|p| !p.is_empty()).map(|p| p.to_owned()).collect();
+        let query = query.split('&')
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+
+        Ok(Request { method, path, query, headers, body })
+    }
+
+    fn json<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        serde_json::from_slice(&self.body).map_err(|e| ApiError::bad_request(&e.to_string()))
+    }
+}
+
+struct ApiError {
+    status: u16,
+    body: serde_json::Value,
+}
+
+impl ApiError {
+    fn bad_request(message: &str) -> ApiError {
+        ApiError { status: 400, body: json!({"error": message}) }
+    }
+
+    fn forbidden() -> ApiError {
+        ApiError { status: 403, body: json!({"error": "Invalid token"}) }
+    }
+
+    fn not_found() -> ApiError {
+        ApiError { status: 404, body: json!({"error": "Not found"}) }
+    }
+
+    fn conflict(message: &str) -> ApiError {
+        ApiError { status: 409, body: json!({"error": message}) }
+    }
+
+    fn unavailable(message: &str) -> ApiError {
+        ApiError { status: 503, body: json!({"error": message}) }
+    }
+
+    fn payload_too_large() -> ApiError {
+        ApiError { status: 413, body: json!({"error": "Request body too large"}) }
+    }
+
+    fn too_large() -> ApiError {
+        ApiError { status: 431, body: json!({"error": "Request headers too large"}) }
+    }
+
+    /// The request couldn't be read from the connection
+    fn read(error: std::io::Error) -> ApiError {
+        match error.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                ApiError { status: 408, body: json!({"error": "Request timed out"}) }
+            }
+            _ => ApiError::bad_request(&error.to_string()),
+        }
+    }
+}
+
+type Response = Result<(u16, serde_json::Value), ApiError>;
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &serde_json::Value) -> Result<(), Error> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    let body = if status == 204 { Vec::new() } else { serde_json::to_vec(body)? };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        reason,
+        body.len(),
+    )?;
+    stream.write_all(&body)?;
+    Ok(())
+}
+
+/// Accept connections and serve the API
+///
+/// The first player to join each game uses the first deck.
+pub fn serve<A: ToSocketAddrs>(address: A, decks: &[DeckList; 2], rules: &RuleSet) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)?;
+    println!("Listening on http://{}/", listener.local_addr()?);
+    run(listener, REQUEST_TIMEOUT, Lobby::new(decks, rules))
+}
+
+/// Serve connections, each having `timeout` to send its request
+fn run(listener: TcpListener, timeout: Duration, lobby: Lobby) -> Result<(), Error> {
+    let shared: Shared = Arc::new((Mutex::new(lobby), Condvar::new()));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, timeout, &shared) {
+                println!("Connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, timeout: Duration, shared: &Shared) -> Result<(), Error> {
+    stream.set_read_timeout(Some(timeout))?;
+    let request = match Request::read(&mut BufReader::new(&mut stream)) {
+        Ok(request) => request,
+        Err(e) => {
+            let _ = write_response(&mut stream, e.status, &e.body);
+            return Err(Error::Protocol(e.body["error"].as_str().unwrap_or_default().to_owned()));
+        }
+    };
+
+    let is_websocket = request.headers.get("upgrade")
+        .map(|u| u.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+    if is_websocket {
+        return stream_events(stream, request, shared);
+    }
+
+    let (status, body) = match handle_request(&request, shared) {
+        Ok(r) => r,
+        Err(e) => (e.status, e.body),
+    };
+    write_response(&mut stream, status, &body)
+}
+
+fn handle_request(request: &Request, shared: &Shared) -> Response {
+    let (lobby, changed) = &**shared;
+    let mut lobby = lobby.lock().unwrap();
+    lobby.purge(Instant::now());
+    let path: Vec<&str> = request.path.iter().map(|s| s.as_str()).collect();
+    let response = match (request.method.as_str(), &path[..]) {
+        ("OPTIONS", _) => Ok((204, serde_json::Value::Null)),
+        ("GET", ["games"]) => {
+            let mut waiting: Vec<u64> = lobby.games.iter()
+                .filter(|(_, g)| g.seats.len() < 2)
+                .map(|(&id, _)| id)
+                .collect();
+            waiting.sort_unstable();
+            Ok((200, json!({"games": waiting})))
+        }
+        ("POST", ["games"]) => {
+            if lobby.games.len() >= lobby.max_games {
+                return Err(ApiError::unavailable("Too many games, try again later"));
+            }
+            lobby.next_id += 1;
+            let id = lobby.next_id;
+            let game = HostedGame::new(&lobby.decks, &lobby.rules, &mut thread_rng());
+            lobby.games.insert(id, game);
+            Ok((201, json!({"game": id})))
+        }
+        (method, ["games", id, endpoint]) => {
+            let id: u64 = id.parse().map_err(|_| ApiError::not_found())?;
+            let game = lobby.games.get_mut(&id).ok_or_else(ApiError::not_found)?;
+            game.updated = Instant::now();
+            if (method, *endpoint) == ("POST", "join") {
+                let (player, token) = game.join(id, &mut thread_rng())
+                    .ok_or_else(|| ApiError::conflict("Game is full"))?;
+                Ok((200, json!({"player": player, "token": token})))
+            } else {
+                let player = request.query.get("token")
+                    .and_then(|t| game.seat(t))
+                    .ok_or_else(ApiError::forbidden)?;
+                match (method, *endpoint) {
+                    ("GET", "view") => Ok((200, json!(game.view(player)))),
+                    ("POST", "mulligan") => {
+                        game.mulligan(player, request.json()?, &mut thread_rng())?;
+                        Ok((200, json!(game.view(player))))
+                    }
+                    ("POST", "actions") => {
+                        let action: Action = request.json()?;
+                        let hosted = game.game.as_mut()
+                            .ok_or_else(|| ApiError::conflict("Game has not started"))?;
+                        match hosted.apply(player, action) {
+                            Ok(events) => {
+                                let visible: Vec<_> = events.iter()
+                                    .map(|e| e.redact_for(player))
+                                    .collect();
+                                game.publish(events);
+                                Ok((200, json!({"events": visible})))
+                            }
+                            Err(e) => Err(ApiError {
+                                status: 409,
+                                body: json!({"error": e.to_string(), "refused": e}),
+                            }),
+                        }
+                    }
+                    _ => Err(ApiError::not_found()),
+                }
+            }
+        }
+        _ => Err(ApiError::not_found()),
+    };
+    changed.notify_all();
+    response
+}
+
+/// Complete the WebSocket handshake and send a player's messages as they come
+fn stream_events(mut stream: TcpStream, request: Request, shared: &Shared) -> Result<(), Error> {
+    let (lobby, changed) = &**shared;
+    let path: Vec<&str> = request.path.iter().map(|s| s.as_str()).collect();
+    let seat = match path[..] {
+        ["games", id, "events"] => {
+            let lobby = lobby.lock().unwrap();
+            id.parse().ok()
+                .and_then(|id: u64| lobby.games.get(&id).map(|g| (id, g)))
+                .and_then(|(id, g)| {
+                    request.query.get("token")
+                        .and_then(|t| g.seat(t))
+                        .map(|player| (id, player))
+                })
+        }
+        _ => None,
+    };
+    let (id, player) = match (seat, request.headers.get("sec-websocket-key")) {
+        (Some(seat), Some(_)) => seat,
+        (None, _) => return write_response(&mut stream, 403, &json!({"error": "Invalid token"})),
+        (_, None) => return write_response(&mut stream, 400, &json!({"error": "Missing key"})),
+    };
+    let key = &request.headers["sec-websocket-key"];
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        tungstenite::handshake::derive_accept_key(key.as_bytes()),
+    )?;
+    // Reads give up quickly, to go back to checking for messages
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    let mut sent = 0;
+    let mut pinged = Instant::now();
+    loop {
+        if !read_client(&mut socket)? {
+            return Ok(());
+        }
+        let messages: Vec<ServerMessage> = {
+            let lobby = lobby.lock().unwrap();
+            let Some(game) = lobby.games.get(&id) else {
+                // The game was dropped
+                return close(socket);
+            };
+            let seat = &game.seats[player as usize];
+            if seat.messages.len() > sent {
+                seat.messages[sent..].to_vec()
+            } else {
+                drop(changed.wait_timeout(lobby, POLL_INTERVAL).unwrap());
+                Vec::new()
+            }
+        };
+        if messages.is_empty() && pinged.elapsed() >= PING_INTERVAL {
+            // Check that the client is still there
+            socket.send(Message::Ping(Vec::new())).map_err(websocket_error)?;
+            pinged = Instant::now();
+        }
+        for message in &messages {
+            socket.send(Message::Text(serde_json::to_string(message)?)).map_err(websocket_error)?;
+            if let ServerMessage::GameOver(_) = message {
+                return close(socket);
+            }
+        }
+        sent += messages.len();
+    }
+}
+
+/// Handle what the client sent, returns false once it closed the WebSocket
+///
+/// Pings are answered by tungstenite, anything else is ignored.
+fn read_client(socket: &mut WebSocket<TcpStream>) -> Result<bool, Error> {
+    loop {
+        match socket.read() {
+            Ok(Message::Close(_)) => {
+                // Send the reply to the close frame
+                let _ = socket.flush();
+                return Ok(false);
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut,
+            ) => {
+                socket.flush().map_err(websocket_error)?;
+                return Ok(true);
+            }
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(false),
+            Err(e) => return Err(websocket_error(e)),
+        }
+    }
+}
+
+/// Close the WebSocket, waiting a little for the client's reply
+fn close(mut socket: WebSocket<TcpStream>) -> Result<(), Error> {
+    socket.close(None).map_err(websocket_error)?;
+    socket.get_ref().set_read_timeout(Some(Duration::from_secs(5)))?;
+    while socket.read().is_ok() {}
+    Ok(())
+}
+
+fn websocket_error(e: tungstenite::Error) -> Error {
+    Error::Protocol(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use std::io::{Read, Write};
+    use rand::thread_rng;
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::time::{Duration, Instant};
+
+    use super::{HostedGame, KEEP_FINISHED, KEEP_IDLE, Lobby, REQUEST_TIMEOUT, run};
+    use superjack::deck::DeckList;
+    use superjack::game::{Action, Suit};
+    use crate::net::ServerMessage;
+    use superjack::rules::RuleSet;
+
+    fn standard() -> [DeckList; 2] {
+        [DeckList::standard(), DeckList::standard()]
+    }
+
+    fn request(address: SocketAddr, method: &str, target: &str, body: Value) -> (u16, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let body = body.to_string();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body,
+        ).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1;
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    #[test]
+    fn test_api() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // The first player only has hearts
+        let hearts = DeckList {
+            cards: DeckList::standard().cards.into_iter()
+                .filter(|&(_, suit)| suit == Suit::Hearts)
+                .collect(),
+        };
+        let rules = RuleSet { starting_life: 30, ..RuleSet::default() };
+        let lobby = Lobby::new(&[hearts, DeckList::standard()], &rules);
+        std::thread::spawn(move || run(listener, REQUEST_TIMEOUT, lobby));
+
+        let (status, body) = request(address, "POST", "/games", Value::Null);
+        assert_eq!(status, 201);
+        let id = body["game"].as_u64().unwrap();
+        assert_eq!(request(address, "GET", "/games", Value::Null).1, json!({"games": [id]}));
+
+        let mut tokens = Vec::new();
+        for player in 0..2 {
+            let (status, body) = request(address, "POST", &format!("/games/{}/join", id), Value::Null);
+            assert_eq!(status, 200);
+            assert_eq!(body["player"], player);
+            tokens.push(body["token"].as_str().unwrap().to_owned());
+        }
+        let (status, _) = request(address, "POST", &format!("/games/{}/join", id), Value::Null);
+        assert_eq!(status, 409);
+        let (status, _) = request(address, "GET", &format!("/games/{}/view?token=nope", id), Value::Null);
+        assert_eq!(status, 403);
+
+        let (_, view) = request(address, "GET", &format!("/games/{}/view?token={}", id, tokens[0]), Value::Null);
+        assert_eq!(view["stage"], "StartingHand");
+        assert_eq!(view["hand"].as_array().unwrap().len(), 5);
+        assert!(view["hand"].as_array().unwrap().iter().all(|c| c["suit"] == "Hearts"));
+        for token in &tokens {
+            let target = format!("/games/{}/mulligan?token={}", id, token);
+            assert_eq!(request(address, "POST", &target, json!(false)).0, 200);
+        }
+
+        // Player 1 sees their hand, player 2 only its size
+        let (_, view) = request(address, "GET", &format!("/games/{}/view?token={}", id, tokens[0]), Value::Null);
+        assert_eq!(view["stage"], "Playing");
+        assert_eq!(view["your_turn"], true);
+        assert_eq!(view["status"]["hand"].as_array().unwrap().len(), 6);
+        assert_eq!(view["status"]["life"], 30);
+        assert_eq!(view["status"]["library"], 4);
+        let (_, view) = request(address, "GET", &format!("/games/{}/view?token={}", id, tokens[1]), Value::Null);
+        assert_eq!(view["your_turn"], false);
+        assert_eq!(view["status"]["enemy_hand"], 6);
+
+        // Events are streamed over the WebSocket
+        let url = format!("ws://{}/games/{}/events?token={}", address, id, tokens[1]);
+        let (mut socket, _) = tungstenite::connect(url).unwrap();
+        let mut receive = || -> ServerMessage {
+            serde_json::from_str(socket.read().unwrap().to_text().unwrap()).unwrap()
+        };
+        assert!(matches!(receive(), ServerMessage::Welcome { player: 1, .. }));
+        assert!(matches!(receive(), ServerMessage::StartingHand { .. }));
+        assert!(matches!(receive(), ServerMessage::Events(_)));
+
+        let target = format!("/games/{}/actions?token={}", id, tokens[1]);
+        assert_eq!(request(address, "POST", &target, json!(Action::Pass)).0, 409);
+        let target = format!("/games/{}/actions?token={}", id, tokens[0]);
+        let (status, body) = request(address, "POST", &target, json!(Action::Pass));
+        assert_eq!(status, 200);
+        assert_eq!(body["events"][0]["PhaseChanged"]["phase"], "Attack");
+        assert!(matches!(receive(), ServerMessage::Events(_)));
+
+        // The server answers when the client closes the WebSocket
+        socket.close(None).unwrap();
+        loop {
+            match socket.read() {
+                Ok(_) => {}
+                Err(tungstenite::Error::ConnectionClosed) => break,
+                Err(e) => panic!("{}", e),
+            }
+        }
+
+        // Huge bodies are refused before reading them
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "POST /games HTTP/1.1\r\nContent-Length: 100000000000\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+    }
+
+    /// Send raw bytes and read the response, stopping early if the server
+    /// closes the connection first
+    fn send_raw(address: SocketAddr, data: &[u8]) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let _ = stream.write_all(data);
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response);
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[test]
+    fn test_limits() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut lobby = Lobby::new(&standard(), &RuleSet::default());
+        lobby.max_games = 2;
+        std::thread::spawn(move || run(listener, Duration::from_millis(200), lobby));
+
+        // A header line that never ends
+        let mut huge = b"GET /games HTTP/1.1\r\nX-Padding: ".to_vec();
+        huge.resize(100_000, b'a');
+        let response = send_raw(address, &huge);
+        assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+
+        // Too many headers
+        let mut many = "GET /games HTTP/1.1\r\n".to_owned();
+        for i in 0..100 {
+            many.push_str(&format!("X-Header-{}: 1\r\n", i));
+        }
+        many.push_str("\r\n");
+        let response = send_raw(address, many.as_bytes());
+        assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+
+        // A client that doesn't send anything is dropped
+        let start = Instant::now();
+        let response = send_raw(address, b"");
+        assert!(response.starts_with("HTTP/1.1 408"), "{}", response);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Requests within the limits still work
+        assert_eq!(request(address, "GET", "/games", Value::Null).0, 200);
+
+        // Games can't be created past the limit
+        assert_eq!(request(address, "POST", "/games", Value::Null).0, 201);
+        assert_eq!(request(address, "POST", "/games", Value::Null).0, 201);
+        let (status, body) = request(address, "POST", "/games", Value::Null);
+        assert_eq!(status, 503);
+        assert!(body["error"].is_string());
+        assert_eq!(request(address, "GET", "/games", Value::Null).1, json!({"games": [1, 2]}));
+    }
+
+    #[test]
+    fn test_purge() {
+        let mut lobby = Lobby::new(&standard(), &RuleSet::default());
+        let mut finished = HostedGame::new(&lobby.decks, &lobby.rules, &mut thread_rng());
+        for player in 0..2 {
+            finished.join(1, &mut thread_rng()).unwrap();
+            assert!(finished.mulligan(player, false, &mut thread_rng()).is_ok());
+        }
+        finished.game.as_mut().unwrap().players[0].life = 0;
+        assert!(finished.is_over());
+        lobby.games.insert(1, finished);
+        let idle = HostedGame::new(&lobby.decks, &lobby.rules, &mut thread_rng());
+        lobby.games.insert(2, idle);
+
+        let now = Instant::now();
+        lobby.purge(now);
+        assert_eq!(lobby.games.len(), 2);
+        lobby.purge(now + KEEP_FINISHED + Duration::from_secs(1));
+        assert!(lobby.games.contains_key(&2) && !lobby.games.contains_key(&1));
+        lobby.purge(now + KEEP_IDLE + Duration::from_secs(1));
+        assert!(lobby.games.is_empty());
+    }
+}